@@ -0,0 +1,56 @@
+-- Databases created before the bot managed its own migrations already contain some of these
+-- tables, so everything in here has to be idempotent.
+
+CREATE TABLE IF NOT EXISTS users (
+	name VARCHAR(255) NOT NULL,
+	discord_id BIGINT NOT NULL UNIQUE,
+	steam_id INT,
+	mode INT4,
+	pb_notifications BOOLEAN NOT NULL DEFAULT FALSE,
+	notification_channel_id BIGINT,
+	last_record_id BIGINT,
+
+	PRIMARY KEY (discord_id)
+);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pb_notifications BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS notification_channel_id BIGINT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_record_id BIGINT;
+
+CREATE TABLE IF NOT EXISTS personal_bests (
+	discord_id BIGINT NOT NULL,
+	map_name VARCHAR(255) NOT NULL,
+	stage INT2 NOT NULL,
+	mode INT2 NOT NULL,
+	runtype BOOLEAN NOT NULL,
+	time FLOAT8 NOT NULL,
+
+	PRIMARY KEY (discord_id, map_name, stage, mode, runtype)
+);
+
+CREATE TABLE IF NOT EXISTS guilds (
+	guild_id BIGINT NOT NULL,
+	mode INT2,
+	ephemeral BOOLEAN,
+	locale VARCHAR(16),
+	wr_feed_channel_id BIGINT,
+	pb_feed_channel_id BIGINT,
+
+	PRIMARY KEY (guild_id)
+);
+
+CREATE TABLE IF NOT EXISTS wr_feed_subscriptions (
+	channel_id BIGINT NOT NULL,
+	guild_id BIGINT NOT NULL,
+	mode INT2,
+	runtype BOOLEAN,
+	tier INT2,
+
+	PRIMARY KEY (channel_id)
+);
+
+CREATE TABLE IF NOT EXISTS wr_feed_records (
+	record_id BIGINT NOT NULL,
+
+	PRIMARY KEY (record_id)
+);
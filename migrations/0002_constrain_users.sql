@@ -0,0 +1,27 @@
+-- `steam_id` stores the 32-bit account ID of a SteamID, which does not fit into a signed INT.
+-- Older versions of the bot wrapped account IDs above 2^31 - 1 around into negative numbers, so
+-- those have to be turned back into the actual account ID.
+ALTER TABLE users
+	ALTER COLUMN steam_id TYPE BIGINT USING (steam_id::BIGINT & 4294967295),
+	ADD CONSTRAINT users_steam_id_check CHECK (steam_id BETWEEN 0 AND 4294967295);
+
+-- Modes are stored as their GOKZ IDs (200 = KZTimer, 201 = SimpleKZ, 202 = Vanilla).
+ALTER TABLE users
+	ALTER COLUMN mode TYPE INT2,
+	ADD CONSTRAINT users_mode_check CHECK (mode IN (200, 201, 202));
+
+ALTER TABLE users
+	ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+	NEW.updated_at = NOW();
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_set_updated_at
+	BEFORE UPDATE ON users
+	FOR EACH ROW
+	EXECUTE FUNCTION set_updated_at();
@@ -0,0 +1,18 @@
+-- Periodic snapshots of linked users' stats, so `/progress` can show how they changed over time.
+-- `tp_completions` and `pro_completions` hold the total at index 0 and tiers 1-7 after that.
+CREATE TABLE progress_snapshots (
+	id BIGSERIAL NOT NULL,
+	steam_id BIGINT NOT NULL CHECK (steam_id BETWEEN 0 AND 4294967295),
+	mode INT2 NOT NULL CHECK (mode IN (200, 201, 202)),
+	tp_points INT4 NOT NULL,
+	pro_points INT4 NOT NULL,
+	tp_wrs INT4 NOT NULL,
+	pro_wrs INT4 NOT NULL,
+	tp_completions INT4[] NOT NULL CHECK (cardinality(tp_completions) = 8),
+	pro_completions INT4[] NOT NULL CHECK (cardinality(pro_completions) = 8),
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+	PRIMARY KEY (id)
+);
+
+CREATE INDEX progress_snapshots_lookup ON progress_snapshots (steam_id, mode, created_at);
@@ -0,0 +1,36 @@
+-- Community competitions on a single map, created with `/competition create`.
+-- `message_id` is the live standings message in `channel_id`, which gets posted once the
+-- competition starts.
+CREATE TABLE competitions (
+	id BIGSERIAL NOT NULL,
+	guild_id BIGINT NOT NULL,
+	channel_id BIGINT NOT NULL,
+	message_id BIGINT,
+	map_name VARCHAR(255) NOT NULL,
+	mode INT2 NOT NULL CHECK (mode IN (200, 201, 202)),
+	runtype BOOLEAN NOT NULL,
+	starts_at TIMESTAMPTZ NOT NULL,
+	ends_at TIMESTAMPTZ NOT NULL CHECK (ends_at > starts_at),
+	started BOOLEAN NOT NULL DEFAULT FALSE,
+	finished BOOLEAN NOT NULL DEFAULT FALSE,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+	PRIMARY KEY (id)
+);
+
+-- Every server can only run one competition at a time.
+CREATE UNIQUE INDEX competitions_one_per_guild ON competitions (guild_id) WHERE NOT finished;
+
+-- Linked members taking part in a competition. `start_time` is their personal best when the
+-- competition started (if they had one), `best_time` the best time they set during it.
+CREATE TABLE competition_entries (
+	competition_id BIGINT NOT NULL REFERENCES competitions (id) ON DELETE CASCADE,
+	steam_id BIGINT NOT NULL CHECK (steam_id BETWEEN 0 AND 4294967295),
+	discord_id BIGINT NOT NULL,
+	player_name VARCHAR(255) NOT NULL,
+	start_time FLOAT8,
+	best_time FLOAT8,
+	best_teleports INT4,
+
+	PRIMARY KEY (competition_id, steam_id)
+);
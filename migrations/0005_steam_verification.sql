@@ -0,0 +1,6 @@
+-- `/setsteam` accepts any SteamID, so links have to be verified with `/verify` before they count for
+-- server leaderboards, competitions and PB notifications. `verification_code` is the code the user
+-- has to put into their Steam name while a verification is pending.
+ALTER TABLE users
+	ADD COLUMN verified BOOLEAN NOT NULL DEFAULT FALSE,
+	ADD COLUMN verification_code VARCHAR(32);
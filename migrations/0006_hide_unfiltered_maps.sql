@@ -0,0 +1,3 @@
+-- Whether map suggestions should leave out maps without a filter for the user's preferred mode.
+-- Set with `/mode`.
+ALTER TABLE users ADD COLUMN hide_unfiltered_maps BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,761 @@
+//! Cache for responses from the GlobalAPI, KZ:GO and SchnoseAPI.
+//!
+//! Many commands fetch the same data over and over again, e.g. `/profile` fetches all of a
+//! player's records and the completion stats for every mode. Calling the API through the
+//! [`ApiCache`] methods of the same name, e.g. [`ApiCache::get_pb`], will serve them from memory
+//! for as long as the [`Endpoint`]'s TTL allows.
+//!
+//! The cache also keeps a [`CircuitBreaker`] for every [`Upstream`] API. While an API is down,
+//! requests to it are not sent at all. Instead we answer with the last response we got, even if it
+//! is outdated, or with [`Error::ApiUnavailable`] if we don't have one.
+
+use {
+	crate::{
+		circuit_breaker::{CircuitBreaker, CircuitState, UpstreamError},
+		error::{Error, Result},
+	},
+	gokz_rs::{
+		global_api::{self, HealthReport},
+		kzgo_api, schnose_api, Mode, PlayerIdentifier, SteamID, Tier,
+	},
+	serde::de::DeserializeOwned,
+	std::{
+		any::Any,
+		collections::HashMap,
+		fmt::Debug,
+		future::Future,
+		ops::Range,
+		sync::{Arc, Mutex},
+		time::{Duration, Instant},
+	},
+	tracing::{error, info, trace, warn},
+};
+
+/// How many requests a single command may have in flight at once when it fetches something for
+/// every member of a server. Without a limit, large servers would flood the APIs.
+pub const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// The APIs we talk to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Upstream {
+	GlobalAPI,
+	KZGO,
+	SchnoseAPI,
+}
+
+impl Upstream {
+	pub const ALL: [Self; 3] = [
+		Self::GlobalAPI,
+		Self::KZGO,
+		Self::SchnoseAPI,
+	];
+}
+
+impl std::fmt::Display for Upstream {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Self::GlobalAPI => "GlobalAPI",
+			Self::KZGO => "KZ:GO API",
+			Self::SchnoseAPI => "SchnoseAPI",
+		})
+	}
+}
+
+/// The different kinds of requests we cache. Each of them has its own TTL and size limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Endpoint {
+	/// `global_api::get_pb`
+	Pb,
+
+	/// `global_api::get_wr`
+	Wr,
+
+	/// `global_api::get_place`
+	Place,
+
+	/// `global_api::get_maptop`
+	Maptop,
+
+	/// `global_api::get_player_records`
+	PlayerRecords,
+
+	/// `global_api::get_wr_top`
+	WrTop,
+
+	/// `global_api::get_unfinished`
+	Unfinished,
+
+	/// `kzgo_api::get_completions`
+	Completions,
+
+	/// `kzgo_api::get_avatar`
+	Avatar,
+
+	/// `schnose_api::get_player`
+	Player,
+
+	/// `schnose_api::get_recent`
+	Recent,
+
+	/// GlobalAPI `/players`, searching by name
+	PlayerSearch,
+
+	/// GlobalAPI `/records/top/recent`, for when a player last set a personal best
+	LastSeen,
+
+	/// GlobalAPI `/records/top`, for every course a player has finished, including bonuses
+	FinishedCourses,
+}
+
+impl Endpoint {
+	pub const ALL: [Self; 14] = [
+		Self::Pb,
+		Self::Wr,
+		Self::Place,
+		Self::Maptop,
+		Self::PlayerRecords,
+		Self::WrTop,
+		Self::Unfinished,
+		Self::Completions,
+		Self::Avatar,
+		Self::Player,
+		Self::Recent,
+		Self::PlayerSearch,
+		Self::LastSeen,
+		Self::FinishedCourses,
+	];
+
+	pub const fn upstream(self) -> Upstream {
+		match self {
+			Self::Pb
+			| Self::Wr
+			| Self::Place
+			| Self::Maptop
+			| Self::PlayerRecords
+			| Self::WrTop
+			| Self::Unfinished
+			| Self::PlayerSearch
+			| Self::LastSeen
+			| Self::FinishedCourses => Upstream::GlobalAPI,
+			Self::Completions | Self::Avatar => Upstream::KZGO,
+			Self::Player | Self::Recent => Upstream::SchnoseAPI,
+		}
+	}
+
+	/// How long a response stays fresh.
+	pub const fn ttl(self) -> Duration {
+		const MINUTE: u64 = 60;
+		const HOUR: u64 = 60 * MINUTE;
+
+		Duration::from_secs(match self {
+			Self::Pb | Self::Wr | Self::Maptop | Self::Recent => MINUTE,
+			Self::Place => 5 * MINUTE,
+			Self::PlayerRecords
+			| Self::Unfinished
+			| Self::FinishedCourses
+			| Self::Player
+			| Self::LastSeen => 5 * MINUTE,
+			Self::PlayerSearch => 15 * MINUTE,
+			Self::WrTop => 15 * MINUTE,
+			Self::Completions => 6 * HOUR,
+			Self::Avatar => 12 * HOUR,
+		})
+	}
+
+	/// How many responses we keep at most.
+	pub const fn capacity(self) -> usize {
+		match self {
+			// These can be several thousand records each.
+			Self::PlayerRecords | Self::Unfinished | Self::FinishedCourses => 64,
+			Self::Completions => 3,
+			Self::WrTop => 12,
+			Self::Pb | Self::Wr | Self::Place | Self::Avatar => 1024,
+			Self::Maptop | Self::Player | Self::Recent | Self::PlayerSearch | Self::LastSeen => 256,
+		}
+	}
+}
+
+/// Hit/miss counters for a single [`Endpoint`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+	pub hits: u64,
+	pub misses: u64,
+	pub entries: usize,
+}
+
+impl Stats {
+	/// Percentage of requests that were served from the cache.
+	pub fn hit_rate(&self) -> f64 {
+		match self.hits + self.misses {
+			0 => 0.0,
+			total => (self.hits as f64 / total as f64) * 100.0,
+		}
+	}
+}
+
+impl std::ops::Add for Stats {
+	type Output = Self;
+
+	fn add(self, rhs: Self) -> Self {
+		Self {
+			hits: self.hits + rhs.hits,
+			misses: self.misses + rhs.misses,
+			entries: self.entries + rhs.entries,
+		}
+	}
+}
+
+/// Shared handle to the response cache. Cloning this is cheap and every clone refers to the same
+/// cache.
+#[derive(Debug, Clone, Default)]
+pub struct ApiCache {
+	buckets: Arc<Mutex<HashMap<Endpoint, Bucket>>>,
+	breakers: Arc<Mutex<HashMap<Upstream, CircuitBreaker>>>,
+}
+
+#[derive(Debug, Default)]
+struct Bucket {
+	entries: HashMap<String, Entry>,
+	hits: u64,
+	misses: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+	value: Arc<dyn Any + Send + Sync>,
+	fetched_at: Instant,
+}
+
+impl ApiCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the cached response for `endpoint` and `params` if it is still fresh. Otherwise
+	/// `fetch` gets awaited and its result is cached if it was successful.
+	///
+	/// `params` should contain every argument that was passed to the API function, so different
+	/// requests don't share an entry.
+	///
+	/// If the upstream API is unavailable, `fetch` is not awaited at all. We fall back to an
+	/// outdated response in that case, if there is one.
+	pub async fn get<T, E>(
+		&self,
+		endpoint: Endpoint,
+		params: impl Debug,
+		fetch: impl Future<Output = std::result::Result<T, E>>,
+	) -> Result<T>
+	where
+		T: Clone + Send + Sync + 'static,
+		E: UpstreamError + Into<Error>,
+	{
+		let key = format!("{params:?}");
+		let upstream = endpoint.upstream();
+		let cached = self.lookup::<T>(endpoint, &key);
+
+		if let Some((value, fetched_at)) = &cached {
+			if fetched_at.elapsed() < endpoint.ttl() {
+				trace!("Cache hit for {endpoint:?} `{key}`.");
+				self.record(endpoint, true);
+				return Ok(value.clone());
+			}
+		}
+
+		trace!("Cache miss for {endpoint:?} `{key}`.");
+		self.record(endpoint, false);
+
+		if !self.breaker(upstream, |breaker| breaker.allows_request(Instant::now())) {
+			return match cached {
+				Some((value, _)) => {
+					info!("{upstream} is unavailable. Serving outdated {endpoint:?} `{key}`.");
+					Ok(value)
+				}
+				None => Err(Error::ApiUnavailable { api: upstream }),
+			};
+		}
+
+		match fetch.await {
+			Ok(value) => {
+				self.breaker(upstream, CircuitBreaker::record_success);
+				self.insert(endpoint, key, value.clone(), Instant::now());
+				Ok(value)
+			}
+			Err(why) if why.is_upstream_failure() => {
+				self.breaker(upstream, |breaker| breaker.record_failure(Instant::now()));
+
+				match cached {
+					Some((value, _)) => {
+						warn!("{upstream} request failed. Serving outdated {endpoint:?} `{key}`.");
+						Ok(value)
+					}
+					None => Err(why.into()),
+				}
+			}
+			Err(why) => {
+				// The API answered, it just didn't have anything for us.
+				self.breaker(upstream, CircuitBreaker::record_success);
+				Err(why.into())
+			}
+		}
+	}
+
+	/// Cached [`global_api::get_pb`].
+	pub async fn get_pb(
+		&self,
+		player: &PlayerIdentifier,
+		map_name: &str,
+		mode: Mode,
+		runtype: bool,
+		course: u8,
+		client: &gokz_rs::Client,
+	) -> Result<global_api::Record> {
+		self.get(
+			Endpoint::Pb,
+			(player, map_name, mode, runtype, course),
+			global_api::get_pb(
+				player.clone(),
+				map_name.to_owned().into(),
+				mode,
+				runtype,
+				course,
+				client,
+			),
+		)
+		.await
+	}
+
+	/// Cached [`global_api::get_wr`].
+	pub async fn get_wr(
+		&self,
+		map_name: &str,
+		mode: Mode,
+		runtype: bool,
+		course: u8,
+		client: &gokz_rs::Client,
+	) -> Result<global_api::Record> {
+		self.get(
+			Endpoint::Wr,
+			(map_name, mode, runtype, course),
+			global_api::get_wr(map_name.to_owned().into(), mode, runtype, course, client),
+		)
+		.await
+	}
+
+	/// Cached [`global_api::get_place`].
+	pub async fn get_place(&self, record_id: u32, client: &gokz_rs::Client) -> Result<u32> {
+		self.get(Endpoint::Place, record_id, global_api::get_place(record_id, client))
+			.await
+	}
+
+	/// Cached [`global_api::get_maptop`].
+	pub async fn get_maptop(
+		&self,
+		map_name: &str,
+		mode: Mode,
+		runtype: bool,
+		course: u8,
+		client: &gokz_rs::Client,
+	) -> Result<Vec<global_api::Record>> {
+		self.get(
+			Endpoint::Maptop,
+			(map_name, mode, runtype, course),
+			global_api::get_maptop(map_name.to_owned().into(), mode, runtype, course, client),
+		)
+		.await
+	}
+
+	/// Cached [`global_api::get_player_records`], fetching all of the player's records at once.
+	pub async fn get_player_records(
+		&self,
+		player: &PlayerIdentifier,
+		mode: Mode,
+		runtype: bool,
+		client: &gokz_rs::Client,
+	) -> Result<Vec<global_api::Record>> {
+		self.get(
+			Endpoint::PlayerRecords,
+			(player, mode, runtype),
+			global_api::get_player_records(player.clone(), mode, runtype, 0, 9999, client),
+		)
+		.await
+	}
+
+	/// Cached [`global_api::get_wr_top`].
+	pub async fn get_wr_top(
+		&self,
+		mode: Mode,
+		runtype: bool,
+		stages: Range<u8>,
+		client: &gokz_rs::Client,
+	) -> Result<Vec<global_api::WorldRecordHolder>> {
+		self.get(
+			Endpoint::WrTop,
+			(mode, runtype, stages.clone()),
+			global_api::get_wr_top(mode, runtype, stages, client),
+		)
+		.await
+	}
+
+	/// Cached [`global_api::get_unfinished`].
+	pub async fn get_unfinished(
+		&self,
+		player: &PlayerIdentifier,
+		mode: Mode,
+		runtype: bool,
+		tier: Option<Tier>,
+		client: &gokz_rs::Client,
+	) -> Result<Option<Vec<global_api::Map>>> {
+		self.get(
+			Endpoint::Unfinished,
+			(player, mode, runtype, tier),
+			global_api::get_unfinished(player.clone(), mode, runtype, tier, client),
+		)
+		.await
+	}
+
+	/// Cached [`kzgo_api::get_completions`].
+	pub async fn get_completions(
+		&self,
+		mode: Mode,
+		client: &gokz_rs::Client,
+	) -> Result<kzgo_api::CompletionStats> {
+		self.get(Endpoint::Completions, mode, kzgo_api::get_completions(mode, client))
+			.await
+	}
+
+	/// Cached [`kzgo_api::get_avatar`]. Only the URL of the avatar is returned.
+	pub async fn get_avatar(&self, steam_id: SteamID, client: &gokz_rs::Client) -> Result<String> {
+		self.get(Endpoint::Avatar, steam_id, kzgo_api::get_avatar(steam_id, client))
+			.await
+			.map(|user| user.avatar_url)
+	}
+
+	/// Cached [`schnose_api::get_player`].
+	pub async fn get_player(
+		&self,
+		player: &PlayerIdentifier,
+		client: &gokz_rs::Client,
+	) -> Result<schnose_api::Player> {
+		self.get(Endpoint::Player, player, schnose_api::get_player(player.clone(), client))
+			.await
+	}
+
+	/// Cached [`schnose_api::get_recent`].
+	pub async fn get_recent(
+		&self,
+		player: &PlayerIdentifier,
+		limit: u32,
+		client: &gokz_rs::Client,
+	) -> Result<Vec<schnose_api::Record>> {
+		self.get(
+			Endpoint::Recent,
+			(player, limit),
+			schnose_api::get_recent(player.clone(), limit, client),
+		)
+		.await
+	}
+
+	/// Cached GET request to a GlobalAPI route that gokz_rs doesn't cover, e.g. `/players`. `query`
+	/// is used as the cache key.
+	pub async fn get_global_api<T>(
+		&self,
+		endpoint: Endpoint,
+		base_url: &str,
+		route: &str,
+		query: &[(&str, &str)],
+		client: &gokz_rs::Client,
+	) -> Result<T>
+	where
+		T: DeserializeOwned + Clone + Send + Sync + 'static,
+	{
+		self.get(endpoint, (route, query), async {
+			client
+				.get(format!("{base_url}{route}"))
+				.query(query)
+				.send()
+				.await?
+				.error_for_status()?
+				.json::<T>()
+				.await
+		})
+		.await
+	}
+
+	/// The current [`CircuitState`] of every upstream API.
+	pub fn circuits(&self) -> Vec<(Upstream, CircuitState)> {
+		let now = Instant::now();
+
+		Upstream::ALL
+			.into_iter()
+			.map(|upstream| (upstream, self.breaker(upstream, |breaker| breaker.state(now))))
+			.collect()
+	}
+
+	/// Spawns a background task that regularly checks the GlobalAPI's health report. If it is
+	/// reporting problems, the GlobalAPI circuit gets opened right away, so users don't have to
+	/// wait for requests to fail first.
+	pub fn spawn_health_check(&self, gokz_client: gokz_rs::Client, interval: Duration) {
+		let cache = self.clone();
+
+		tokio::spawn(async move {
+			let mut interval = tokio::time::interval(interval);
+
+			loop {
+				interval.tick().await;
+
+				trace!("Checking GlobalAPI health.");
+
+				match global_api::checkhealth(&gokz_client).await {
+					Ok(HealthReport { successful_responses, .. }) if successful_responses < 5 => {
+						warn!("GlobalAPI health check reported {successful_responses}/10 successful responses.");
+						cache.breaker(Upstream::GlobalAPI, |breaker| breaker.open(Instant::now()));
+					}
+					Ok(_) => {}
+					Err(why) => error!("Failed to check GlobalAPI health: {why:?}"),
+				}
+			}
+		});
+	}
+
+	fn breaker<R>(&self, upstream: Upstream, f: impl FnOnce(&mut CircuitBreaker) -> R) -> R {
+		let mut breakers = self
+			.breakers
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		f(breakers.entry(upstream).or_default())
+	}
+
+	/// Hit/miss counters for every endpoint.
+	pub fn stats(&self) -> Vec<(Endpoint, Stats)> {
+		let buckets = self.lock();
+
+		Endpoint::ALL
+			.into_iter()
+			.map(|endpoint| {
+				let stats = buckets
+					.get(&endpoint)
+					.map(|bucket| Stats {
+						hits: bucket.hits,
+						misses: bucket.misses,
+						entries: bucket.entries.len(),
+					})
+					.unwrap_or_default();
+
+				(endpoint, stats)
+			})
+			.collect()
+	}
+
+	/// Hit/miss counters summed up over all endpoints.
+	pub fn total_stats(&self) -> Stats {
+		self.stats()
+			.into_iter()
+			.fold(Stats::default(), |total, (_, stats)| total + stats)
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Endpoint, Bucket>> {
+		// Nothing in here can panic while holding the lock in a way that leaves the map in a bad
+		// state, so we just keep going.
+		self.buckets
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	/// The cached response for `key`, no matter how old it is.
+	fn lookup<T: Clone + 'static>(&self, endpoint: Endpoint, key: &str) -> Option<(T, Instant)> {
+		self.lock()
+			.get(&endpoint)?
+			.entries
+			.get(key)
+			.and_then(|entry| {
+				entry
+					.value
+					.downcast_ref::<T>()
+					.map(|value| (value.clone(), entry.fetched_at))
+			})
+	}
+
+	fn record(&self, endpoint: Endpoint, hit: bool) {
+		let mut buckets = self.lock();
+		let bucket = buckets.entry(endpoint).or_default();
+
+		if hit {
+			bucket.hits += 1;
+		} else {
+			bucket.misses += 1;
+		}
+	}
+
+	fn insert<T: Send + Sync + 'static>(
+		&self,
+		endpoint: Endpoint,
+		key: String,
+		value: T,
+		now: Instant,
+	) {
+		let mut buckets = self.lock();
+		let bucket = buckets.entry(endpoint).or_default();
+
+		if bucket.entries.len() >= endpoint.capacity() && !bucket.entries.contains_key(&key) {
+			// Make room by dropping the oldest entry.
+			if let Some(oldest) = bucket
+				.entries
+				.iter()
+				.min_by_key(|(_, entry)| entry.fetched_at)
+				.map(|(key, _)| key.clone())
+			{
+				bucket.entries.remove(&oldest);
+			}
+		}
+
+		bucket
+			.entries
+			.insert(key, Entry { value: Arc::new(value), fetched_at: now });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Debug, PartialEq, Eq)]
+	enum TestError {
+		NotFound,
+		Down,
+	}
+
+	impl UpstreamError for TestError {
+		fn is_upstream_failure(&self) -> bool {
+			*self == Self::Down
+		}
+	}
+
+	impl From<TestError> for Error {
+		fn from(error: TestError) -> Self {
+			Self::Custom(format!("{error:?}"))
+		}
+	}
+
+	#[tokio::test]
+	async fn hits_and_misses() {
+		let cache = ApiCache::new();
+
+		let first = cache
+			.get(Endpoint::Place, 1, async { Ok::<u32, TestError>(7) })
+			.await;
+		assert_eq!(first, Ok(7));
+
+		// The second request is served from the cache, so this value never shows up.
+		let second = cache
+			.get(Endpoint::Place, 1, async { Ok::<u32, TestError>(100) })
+			.await;
+		assert_eq!(second, Ok(7));
+
+		// Different params get a different entry.
+		let other = cache
+			.get(Endpoint::Place, 2, async { Ok::<u32, TestError>(3) })
+			.await;
+		assert_eq!(other, Ok(3));
+
+		let stats = cache.total_stats();
+		assert_eq!(stats, Stats { hits: 1, misses: 2, entries: 2 });
+	}
+
+	#[tokio::test]
+	async fn errors_are_not_cached() {
+		let cache = ApiCache::new();
+
+		let failed = cache
+			.get(Endpoint::Pb, "map", async { Err::<u32, _>(TestError::NotFound) })
+			.await;
+		assert_eq!(failed, Err(Error::Custom(String::from("NotFound"))));
+
+		let retried = cache
+			.get(Endpoint::Pb, "map", async { Ok::<_, TestError>(1) })
+			.await;
+		assert_eq!(retried, Ok(1));
+	}
+
+	#[tokio::test]
+	async fn open_circuit() {
+		let cache = ApiCache::new();
+
+		for _ in 0..crate::circuit_breaker::FAILURE_THRESHOLD {
+			let failed = cache
+				.get(Endpoint::Pb, "map", async { Err::<u32, _>(TestError::Down) })
+				.await;
+			assert_eq!(failed, Err(Error::Custom(String::from("Down"))));
+		}
+
+		// The request would succeed now, but we don't even try.
+		let unavailable = cache
+			.get(Endpoint::Pb, "map", async { Ok::<u32, TestError>(1) })
+			.await;
+		assert_eq!(unavailable, Err(Error::ApiUnavailable { api: Upstream::GlobalAPI }));
+
+		// Other APIs are not affected.
+		let avatar = cache
+			.get(Endpoint::Avatar, "player", async { Ok::<u32, TestError>(1) })
+			.await;
+		assert_eq!(avatar, Ok(1));
+	}
+
+	#[tokio::test]
+	async fn stale_fallback() {
+		let cache = ApiCache::new();
+
+		cache.insert(
+			Endpoint::Wr,
+			format!("{:?}", "map"),
+			1u32,
+			Instant::now() - Endpoint::Wr.ttl(),
+		);
+
+		// The entry is outdated, but better than nothing if the API is down.
+		let stale = cache
+			.get(Endpoint::Wr, "map", async { Err::<u32, _>(TestError::Down) })
+			.await;
+		assert_eq!(stale, Ok(1));
+
+		cache.breaker(Upstream::GlobalAPI, |breaker| breaker.open(Instant::now()));
+
+		let stale = cache
+			.get(Endpoint::Wr, "map", async { Ok::<u32, TestError>(2) })
+			.await;
+		assert_eq!(stale, Ok(1));
+	}
+
+	#[test]
+	fn capacity() {
+		let cache = ApiCache::new();
+		let now = Instant::now();
+		let capacity = Endpoint::Completions.capacity();
+
+		for i in 0..=capacity {
+			cache.insert(
+				Endpoint::Completions,
+				i.to_string(),
+				i,
+				now + Duration::from_secs(i as u64),
+			);
+		}
+
+		let stats = cache.stats();
+		let (_, completions) = stats
+			.iter()
+			.find(|(endpoint, _)| *endpoint == Endpoint::Completions)
+			.unwrap();
+
+		assert_eq!(completions.entries, capacity);
+
+		// The oldest entry got evicted.
+		assert_eq!(cache.lookup::<usize>(Endpoint::Completions, "0"), None);
+		assert_eq!(
+			cache
+				.lookup::<usize>(Endpoint::Completions, "1")
+				.map(|(value, _)| value),
+			Some(1)
+		);
+	}
+}
@@ -0,0 +1,153 @@
+//! Keeps track of whether an upstream API is currently healthy.
+//!
+//! If too many requests to an API fail in a row, the circuit "opens" and we stop sending requests
+//! to that API for a while. Users get an immediate answer instead of waiting for yet another
+//! timeout. After the cooldown, requests are let through again, and the first successful one
+//! closes the circuit.
+
+use std::time::{Duration, Instant};
+
+/// How many requests in a row have to fail before the circuit opens.
+pub const FAILURE_THRESHOLD: u32 = 5;
+
+/// How long the circuit stays open before we try again.
+pub const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Errors that might indicate that an upstream API is having problems, as opposed to errors that
+/// just mean that the API had nothing to give us.
+pub trait UpstreamError {
+	fn is_upstream_failure(&self) -> bool;
+}
+
+impl UpstreamError for gokz_rs::Error {
+	fn is_upstream_failure(&self) -> bool {
+		match self {
+			// Timeouts, connection errors etc.
+			gokz_rs::Error::Reqwest { .. } => true,
+			gokz_rs::Error::Http { status_code, .. } => status_code.is_server_error(),
+			_ => false,
+		}
+	}
+}
+
+impl UpstreamError for reqwest::Error {
+	fn is_upstream_failure(&self) -> bool {
+		match self.status() {
+			Some(status_code) => status_code.is_server_error(),
+			// Timeouts, connection errors etc., but not responses we failed to parse.
+			None => !self.is_decode(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+	/// Everything is fine.
+	Closed,
+
+	/// Requests are not being sent.
+	Open,
+
+	/// The cooldown is over and requests are being let through again, but the last request still
+	/// failed.
+	HalfOpen,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CircuitBreaker {
+	consecutive_failures: u32,
+	opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+	pub fn state(&self, now: Instant) -> CircuitState {
+		match self.opened_at {
+			None => CircuitState::Closed,
+			Some(opened_at) if now.duration_since(opened_at) < COOLDOWN => CircuitState::Open,
+			Some(_) => CircuitState::HalfOpen,
+		}
+	}
+
+	/// Whether a request should be sent at all.
+	pub fn allows_request(&self, now: Instant) -> bool {
+		self.state(now) != CircuitState::Open
+	}
+
+	pub fn record_success(&mut self) {
+		self.consecutive_failures = 0;
+		self.opened_at = None;
+	}
+
+	pub fn record_failure(&mut self, now: Instant) {
+		self.consecutive_failures = self
+			.consecutive_failures
+			.saturating_add(1);
+
+		if self.consecutive_failures >= FAILURE_THRESHOLD {
+			self.opened_at = Some(now);
+		}
+	}
+
+	/// Opens the circuit regardless of how many requests failed, e.g. because the API's health
+	/// check is reporting problems.
+	pub fn open(&mut self, now: Instant) {
+		self.consecutive_failures = self
+			.consecutive_failures
+			.max(FAILURE_THRESHOLD);
+		self.opened_at = Some(now);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn opens_after_repeated_failures() {
+		let mut breaker = CircuitBreaker::default();
+		let now = Instant::now();
+
+		for _ in 1..FAILURE_THRESHOLD {
+			breaker.record_failure(now);
+			assert_eq!(breaker.state(now), CircuitState::Closed);
+		}
+
+		breaker.record_failure(now);
+		assert_eq!(breaker.state(now), CircuitState::Open);
+		assert!(!breaker.allows_request(now));
+	}
+
+	#[test]
+	fn success_resets_failures() {
+		let mut breaker = CircuitBreaker::default();
+		let now = Instant::now();
+
+		for _ in 1..FAILURE_THRESHOLD {
+			breaker.record_failure(now);
+		}
+
+		breaker.record_success();
+		breaker.record_failure(now);
+		assert_eq!(breaker.state(now), CircuitState::Closed);
+	}
+
+	#[test]
+	fn half_open_after_cooldown() {
+		let mut breaker = CircuitBreaker::default();
+		let now = Instant::now();
+
+		breaker.open(now);
+		assert_eq!(breaker.state(now), CircuitState::Open);
+
+		let later = now + COOLDOWN;
+		assert_eq!(breaker.state(later), CircuitState::HalfOpen);
+		assert!(breaker.allows_request(later));
+
+		// A single failure while half-open is enough to open the circuit again.
+		breaker.record_failure(later);
+		assert_eq!(breaker.state(later), CircuitState::Open);
+
+		breaker.record_success();
+		assert_eq!(breaker.state(later), CircuitState::Closed);
+	}
+}
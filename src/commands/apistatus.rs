@@ -1,5 +1,6 @@
 use {
 	crate::{
+		circuit_breaker::CircuitState,
 		error::{Error, Result},
 		state::{Context, StateContainer},
 	},
@@ -14,11 +15,13 @@ use {
 /// will give you some information about the \
 /// [GlobalAPI](https://kztimerglobal.com/swagger/index.html?urls.primaryName=V2)'s current \
 /// status. It uses [this website](https://health.global-api.com/endpoints/_globalapi) for \
-/// fetching that information and displays different messages depending on the current stats.
+/// fetching that information and displays different messages depending on the current stats. \
+/// It also shows how many API requests the bot could answer from its own cache, and which APIs \
+/// the bot currently considers unavailable.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn apistatus(ctx: Context<'_>) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let HealthReport { successful_responses, fast_responses } =
 		global_api::checkhealth(ctx.gokz_client()).await?;
@@ -33,6 +36,24 @@ pub async fn apistatus(ctx: Context<'_>) -> Result<()> {
 		_ => ("zer0.k wanted to be funny and pulled the usb stick again", (243, 139, 168)),
 	};
 
+	let cache = ctx.api_cache().total_stats();
+
+	let circuits = ctx
+		.api_cache()
+		.circuits()
+		.into_iter()
+		.map(|(api, state)| {
+			let state = match state {
+				CircuitState::Closed => "✅ Available",
+				CircuitState::HalfOpen => "⚠️ Recovering",
+				CircuitState::Open => "❌ Unavailable",
+			};
+
+			format!("{api}: {state}")
+		})
+		.collect::<Vec<_>>()
+		.join("\n");
+
 	ctx.send(|reply| {
 		reply.embed(|embed| {
 			embed.color(color)
@@ -41,6 +62,12 @@ pub async fn apistatus(ctx: Context<'_>) -> Result<()> {
 				.thumbnail("https://dka575ofm4ao0.cloudfront.net/pages-transactional_logos/retina/74372/kz-icon.png")
 				.field("Successful Healthchecks", format!("{successful_responses} / {}", 10), true)
 				.field("Fast Responses", format!("{fast_responses} / {}", 10), true)
+				.field(
+					"Cache Hit Rate",
+					format!("{:.1}% ({} / {})", cache.hit_rate(), cache.hits, cache.hits + cache.misses),
+					false,
+				)
+				.field("Upstream APIs", circuits, false)
 				.footer(|footer| footer.text(ctx.schnose()).icon_url(ctx.icon_url()))
 		})
 	}).await?;
@@ -1,17 +1,185 @@
 use {
-	crate::state::{Context, StateContainer},
+	crate::{
+		database::UserRepository,
+		state::{Context, StateContainer},
+		utils,
+	},
+	gokz_rs::SteamID,
+	poise::{serenity_prelude::UserId, AutocompleteChoice},
 	schnosebot::global_map::GlobalMap,
 };
 
 // Provides autocompletion for map names on certain commands using some fuzzy finding algorithm
 // I found on the interent. :)
+//
+// Every suggestion shows the map's tier and filters, e.g. `kz_epiphany_v2 — T3 · KZT/SKZ`. Maps
+// the user has recently looked up come first, and if they asked for it with `/mode`, maps without
+// a filter for their preferred mode are left out.
 pub async fn map_name<'a>(
 	ctx: Context<'a>,
 	input: &'a str,
-) -> impl futures::Stream<Item = String> + 'a {
+) -> impl futures::Stream<Item = AutocompleteChoice<String>> + 'a {
+	let hide_unfiltered_for = ctx
+		.fetch_user_by_id(ctx.author_id())
+		.await
+		.filter(|user| user.hide_unfiltered_maps)
+		.and_then(|user| user.mode);
+
+	let recent_maps = ctx.recent_maps().get(ctx.author_id());
+
+	let mut maps = GlobalMap::fuzzy_match(input, &ctx.maps())
+		.into_iter()
+		.filter(|map| hide_unfiltered_for.map_or(true, |mode| utils::has_filter(map, mode)))
+		.collect::<Vec<_>>();
+
+	// This is a stable sort, so everything else keeps the fuzzy finder's order.
+	maps.sort_by_key(|map| {
+		recent_maps
+			.iter()
+			.position(|recent| *recent == map.name)
+			.unwrap_or(usize::MAX)
+	});
+
+	futures::stream::iter(
+		maps.into_iter()
+			// Discord doesn't allow more than 25 suggestions.
+			.take(25)
+			.map(|map| AutocompleteChoice {
+				name: utils::describe_map(&map),
+				value: map.name,
+			}),
+	)
+}
+
+// Suggests users who linked their SteamID, members of the current server first, followed by
+// players that have recently been looked up by anyone. Linked users are matched by name, or found
+// directly if the input is a full SteamID. The value is always a SteamID, so the command doesn't
+// have to guess who was meant.
+//
+// Discord only waits 3 seconds for suggestions, so this sticks to a single database query and
+// Serenity's cache instead of asking Discord about every member.
+pub async fn player_name<'a>(
+	ctx: Context<'a>,
+	input: &'a str,
+) -> impl futures::Stream<Item = AutocompleteChoice<String>> + 'a {
+	let steam_id = SteamID::new(input.trim()).ok();
+	let input = input.trim().to_lowercase();
+
+	let mut linked = ctx
+		.users()
+		// Discord doesn't allow more than 25 suggestions.
+		.suggest_linked(&input, 25)
+		.await
+		.unwrap_or_default();
+
+	if let Some(steam_id) = steam_id {
+		if let Ok(Some(user)) = ctx
+			.users()
+			.get_by_steam_id(steam_id)
+			.await
+		{
+			linked.insert(0, user);
+		}
+	}
+
+	let mut linked = linked
+		.into_iter()
+		.filter_map(|user| Some((user.discord_id, user.name, user.steam_id?)))
+		.collect::<Vec<_>>();
+
+	if let Some(guild_id) = ctx.guild_id() {
+		let cache = &ctx.serenity_context().cache;
+
+		// This is a stable sort, so everyone else keeps the database's order.
+		linked.sort_by_key(|(discord_id, ..)| {
+			cache
+				.member(guild_id, UserId(*discord_id))
+				.is_none()
+		});
+	}
+
+	let linked = linked
+		.into_iter()
+		.map(|(_, name, steam_id)| (name, steam_id));
+
+	let recent = ctx
+		.recent_players()
+		.get()
+		.into_iter()
+		.filter(|(name, steam_id)| {
+			name.to_lowercase().contains(&input)
+				|| steam_id
+					.to_string()
+					.to_lowercase()
+					.contains(&input)
+		});
+
+	let mut players = Vec::<(String, _)>::new();
+
+	for (name, steam_id) in linked.chain(recent) {
+		if !players
+			.iter()
+			.any(|(_, known)| *known == steam_id)
+		{
+			players.push((name, steam_id));
+		}
+	}
+
 	futures::stream::iter(
-		GlobalMap::fuzzy_match(input, ctx.maps())
+		players
 			.into_iter()
-			.map(|map| map.name),
+			// Discord doesn't allow more than 25 suggestions.
+			.take(25)
+			.map(|(name, steam_id)| AutocompleteChoice {
+				name: format!("{name} ({steam_id})"),
+				value: steam_id.to_string(),
+			}),
 	)
 }
+
+// Suggests the bonuses of the map that has been entered into the `map` option of the same
+// command. Nothing is suggested until a map has been picked.
+pub async fn course<'a>(
+	ctx: Context<'a>,
+	input: &'a str,
+) -> impl futures::Stream<Item = AutocompleteChoice<u8>> + 'a {
+	let map = match ctx {
+		poise::Context::Application(app_ctx) => app_ctx
+			.args
+			.iter()
+			.find(|option| option.name == "map")
+			.and_then(|option| option.value.as_ref())
+			.and_then(|value| value.as_str())
+			.and_then(|map_name| ctx.get_map(map_name.to_owned()).ok()),
+		poise::Context::Prefix(_) => None,
+	};
+
+	let mut bonuses = map
+		.map(|map| {
+			map.courses
+				.into_iter()
+				.filter(|course| course.stage != 0)
+				.filter(|course| {
+					course
+						.stage
+						.to_string()
+						.starts_with(input.trim())
+				})
+				.collect::<Vec<_>>()
+		})
+		.unwrap_or_default();
+
+	bonuses.sort_by_key(|course| course.stage);
+
+	let choices = bonuses
+		.into_iter()
+		// Discord doesn't allow more than 25 suggestions.
+		.take(25)
+		.map(|course| AutocompleteChoice {
+			name: format!("B{} (T{})", course.stage, course.tier as u8),
+			value: course.stage,
+		})
+		.collect::<Vec<_>>();
+
+	futures::stream::iter(choices)
+}
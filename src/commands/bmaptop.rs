@@ -7,8 +7,8 @@ use {
 	crate::{
 		error::{Error, Result},
 		state::{Context, StateContainer},
+		utils,
 	},
-	gokz_rs::global_api,
 	poise::serenity_prelude::CreateEmbed,
 	schnosebot::time,
 };
@@ -20,13 +20,14 @@ use {
 /// following options:
 ///
 /// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-///     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-///     preference in the database, see `/mode`.
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
 /// - `runtype`: `TP` / `PRO`
 ///   - If you don't specify this, the bot will default to `PRO`.
 /// - `course`: this can be any integer between 1-255.
 ///   - If you either don't specify this, or put in `0`, the bot will default to `1`.
+///   - If the map doesn't have that many bonuses, the command will fail and list the valid ones.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn bmaptop(
@@ -47,11 +48,14 @@ pub async fn bmaptop(
 
 	#[description = "Which bonus?"]
 	#[rename = "course"]
+	#[autocomplete = "autocomplete::course"]
 	course_choice: Option<u8>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let map = ctx.get_map(map_choice.clone())?;
+	ctx.recent_maps()
+		.remember(ctx.author_id(), &map.name);
 
 	let mode = match mode_choice {
 		Some(choice) => choice.into(),
@@ -60,11 +64,12 @@ pub async fn bmaptop(
 
 	let runtype = matches!(runtype_choice, Some(RuntypeChoice::TP));
 
-	let course = course_choice.unwrap_or(1).max(1);
+	let course = utils::bonus_course(&map, course_choice)?;
 
-	let maptop =
-		global_api::get_maptop(map.name.clone().into(), mode, runtype, course, ctx.gokz_client())
-			.await?;
+	let maptop = ctx
+		.api_cache()
+		.get_maptop(&map.name, mode, runtype, course, ctx.gokz_client())
+		.await?;
 
 	let mut embeds = Vec::new();
 	let mut temp = CreateEmbed::default();
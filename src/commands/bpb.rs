@@ -4,8 +4,9 @@ use {
 		error::{Error, Result},
 		state::{Context, StateContainer},
 		target::Target,
+		utils,
 	},
-	gokz_rs::{global_api, PlayerIdentifier},
+	gokz_rs::PlayerIdentifier,
 	schnosebot::time,
 };
 
@@ -15,19 +16,22 @@ use {
 /// specify a `map` and may also specify the following options:
 ///
 /// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-///     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-///     preference in the database, see `/mode`.
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
 /// - `player`: this can be any string. The bot will try its best to interpret it as something \
 ///   useful. If you want to help it with that, specify one of the following:
 ///   - a `SteamID`, e.g. `STEAM_1:1:161178172`, `U:1:322356345` or `76561198282622073`
+///   - a link to a Steam or KZ:GO profile, e.g. `https://steamcommunity.com/id/alphakeks`
 ///   - a `Mention`, e.g. `@MyBestFriend`
-///   - a player's name, e.g. `AlphaKeks`
+///   - a player's name, e.g. `AlphaKeks`. If several players match, the bot will ask you \
+///     which one you mean.
 ///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
 ///     find one, or you don't have a SteamID set, the command will fail. To save a mode \
 ///     preference in the database, see `/setsteam`.
 /// - `course`: this can be any integer between 1-255.
 ///   - If you either don't specify this, or put in `0`, the bot will default to `1`.
+///   - If the map doesn't have that many bonuses, the command will fail and list the valid ones.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn bpb(
@@ -44,15 +48,19 @@ pub async fn bpb(
 
 	#[description = "The player you want to target."]
 	#[rename = "player"]
+	#[autocomplete = "autocomplete::player_name"]
 	target: Option<String>,
 
 	#[description = "Which bonus?"]
 	#[rename = "course"]
+	#[autocomplete = "autocomplete::course"]
 	course_choice: Option<u8>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let map = ctx.get_map(map_choice.clone())?;
+	ctx.recent_maps()
+		.remember(ctx.author_id(), &map.name);
 
 	let mode = match mode_choice {
 		Some(choice) => choice.into(),
@@ -64,29 +72,19 @@ pub async fn bpb(
 		Some(target) => target.parse()?,
 	};
 
-	let player = target.into_player(&ctx).await;
-
-	let course = course_choice.unwrap_or(1).max(1);
-
-	let tp_pb = global_api::get_pb(
-		player.clone(),
-		map.name.clone().into(),
-		mode,
-		true,
-		course,
-		ctx.gokz_client(),
-	)
-	.await;
-
-	let pro_pb = global_api::get_pb(
-		player.clone(),
-		map.name.clone().into(),
-		mode,
-		false,
-		course,
-		ctx.gokz_client(),
-	)
-	.await;
+	let player = target.into_player(&ctx).await?;
+
+	let course = utils::bonus_course(&map, course_choice)?;
+
+	let tp_pb = ctx
+		.api_cache()
+		.get_pb(&player, &map.name, mode, true, course, ctx.gokz_client())
+		.await;
+
+	let pro_pb = ctx
+		.api_cache()
+		.get_pb(&player, &map.name, mode, false, course, ctx.gokz_client())
+		.await;
 
 	let mut player_name = match player {
 		PlayerIdentifier::Name(player_name) => player_name.clone(),
@@ -95,8 +93,8 @@ pub async fn bpb(
 
 	let mut player_steam_id = None;
 
-	if tp_pb.is_err() && pro_pb.is_err() {
-		return Err(Error::NoRecords);
+	if let (Err(why), Err(_)) = (&tp_pb, &pro_pb) {
+		return Err(why.clone().or_no_records());
 	}
 
 	let (tp_time, tp_links) = match &tp_pb {
@@ -105,7 +103,9 @@ pub async fn bpb(
 			player_name = pb.player_name.clone();
 			player_steam_id = Some(pb.steam_id);
 
-			let place = global_api::get_place(pb.id, ctx.gokz_client())
+			let place = ctx
+				.api_cache()
+				.get_place(pb.id, ctx.gokz_client())
 				.await
 				.map(|place| format!("[#{place}]"))
 				.unwrap_or_default();
@@ -130,7 +130,9 @@ pub async fn bpb(
 			player_name = pb.player_name.clone();
 			player_steam_id = Some(pb.steam_id);
 
-			let place = global_api::get_place(pb.id, ctx.gokz_client())
+			let place = ctx
+				.api_cache()
+				.get_place(pb.id, ctx.gokz_client())
 				.await
 				.map(|place| format!("[#{place}]"))
 				.unwrap_or_default();
@@ -141,6 +143,11 @@ pub async fn bpb(
 		}
 	};
 
+	if let Some(steam_id) = player_steam_id {
+		ctx.recent_players()
+			.remember(&player_name, steam_id);
+	}
+
 	let player_links = player_steam_id
 		.map(|steam_id| {
 			format!(
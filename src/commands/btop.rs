@@ -7,7 +7,6 @@ use {
 		error::{Error, Result},
 		state::{Context, StateContainer},
 	},
-	gokz_rs::{global_api, kzgo_api},
 	poise::serenity_prelude::CreateEmbed,
 };
 
@@ -17,9 +16,9 @@ use {
 /// following options:
 ///
 /// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-///     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-///     preference in the database, see `/mode`.
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
 /// - `runtype`: `TP` / `PRO`
 ///   - If you don't specify this, the bot will default to `PRO`.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
@@ -35,7 +34,7 @@ pub async fn btop(
 	#[rename = "runtype"]
 	runtype_choice: Option<RuntypeChoice>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let mode = match mode_choice {
 		Some(choice) => choice.into(),
@@ -44,7 +43,9 @@ pub async fn btop(
 
 	let runtype = matches!(runtype_choice, Some(RuntypeChoice::TP));
 
-	let top = global_api::get_wr_top(mode, runtype, 1..101, ctx.gokz_client())
+	let top = ctx
+		.api_cache()
+		.get_wr_top(mode, runtype, 1..101, ctx.gokz_client())
 		.await?
 		.into_iter()
 		.take(100)
@@ -55,9 +56,9 @@ pub async fn btop(
 		.map(|player| player.steam_id)
 	{
 		None => String::new(),
-		Some(steam_id) => kzgo_api::get_avatar(steam_id, ctx.gokz_client())
+		Some(steam_id) => ctx
+			.fetch_avatar(steam_id)
 			.await
-			.map(|user| user.avatar_url)
 			.unwrap_or_default(),
 	};
 
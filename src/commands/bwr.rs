@@ -3,8 +3,8 @@ use {
 	crate::{
 		error::{Error, Result},
 		state::{Context, StateContainer},
+		utils,
 	},
-	gokz_rs::global_api,
 	schnosebot::time,
 };
 
@@ -14,11 +14,12 @@ use {
 // `map` and may also specify the following options:
 //
 // - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-//   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-//     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-//     preference in the database, see `/mode`.
+//   - If you don't specify this, the bot will search the database for your UserID and use your \
+//     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+//     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
 // - `course`: this can be any integer between 1-255.
 //   - If you either don't specify this, or put in `0`, the bot will default to `1`.
+//   - If the map doesn't have that many bonuses, the command will fail and list the valid ones.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn bwr(
@@ -35,27 +36,34 @@ pub async fn bwr(
 
 	#[description = "Which bonus?"]
 	#[rename = "course"]
+	#[autocomplete = "autocomplete::course"]
 	course_choice: Option<u8>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let map = ctx.get_map(map_choice.clone())?;
+	ctx.recent_maps()
+		.remember(ctx.author_id(), &map.name);
 
 	let mode = match mode_choice {
 		Some(choice) => choice.into(),
 		None => ModeChoice::figure_out(ctx.author_id().into(), &ctx).await,
 	};
 
-	let course = course_choice.unwrap_or(1).max(1);
+	let course = utils::bonus_course(&map, course_choice)?;
 
-	let tp_wr =
-		global_api::get_wr(map.name.clone().into(), mode, true, course, ctx.gokz_client()).await;
+	let tp_wr = ctx
+		.api_cache()
+		.get_wr(&map.name, mode, true, course, ctx.gokz_client())
+		.await;
 
-	let pro_wr =
-		global_api::get_wr(map.name.clone().into(), mode, false, course, ctx.gokz_client()).await;
+	let pro_wr = ctx
+		.api_cache()
+		.get_wr(&map.name, mode, false, course, ctx.gokz_client())
+		.await;
 
-	if tp_wr.is_err() && pro_wr.is_err() {
-		return Err(Error::NoRecords);
+	if let (Err(why), Err(_)) = (&tp_wr, &pro_wr) {
+		return Err(why.clone().or_no_records());
 	}
 
 	let (tp_time, tp_links) = match &tp_wr {
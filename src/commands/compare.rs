@@ -0,0 +1,266 @@
+use {
+	super::{
+		custom_params::{ModeChoice, RuntypeChoice},
+		pagination,
+	},
+	crate::{
+		error::{Error, Result},
+		state::{Context, StateContainer},
+		target::Target,
+	},
+	poise::serenity_prelude::CreateEmbed,
+	schnosebot::time,
+	std::collections::{BTreeMap, HashMap},
+};
+
+/// Compare two players' records.
+///
+/// This command will fetch all records of two players in a particular mode and compare them. It \
+/// will show you which maps both players have finished and who is faster on them, which maps only \
+/// one of them has finished, how many maps each player "wins" per tier, and the difference in \
+/// points. You are required to specify two players and may also specify the following options:
+///
+/// - `player1` / `player2`: this can be any string. The bot will try its best to interpret it as \
+///   something useful. If you want to help it with that, specify one of the following:
+///   - a `SteamID`, e.g. `STEAM_1:1:161178172`, `U:1:322356345` or `76561198282622073`
+///   - a link to a Steam or KZ:GO profile, e.g. `https://steamcommunity.com/id/alphakeks`
+///   - a `Mention`, e.g. `@MyBestFriend`
+///   - a player's name, e.g. `AlphaKeks`. If several players match, the bot will ask you \
+///     which one you mean.
+/// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
+/// - `runtype`: `TP` / `PRO`
+///   - If you don't specify this, the bot will default to `PRO`.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
+pub async fn compare(
+	ctx: Context<'_>,
+
+	#[description = "The first player."]
+	#[rename = "player1"]
+	target1: String,
+
+	#[description = "The second player."]
+	#[rename = "player2"]
+	target2: String,
+
+	#[description = "KZT/SKZ/VNL"]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "TP/PRO"]
+	#[rename = "runtype"]
+	runtype_choice: Option<RuntypeChoice>,
+) -> Result<()> {
+	ctx.defer_reply().await?;
+
+	let mode = match mode_choice {
+		Some(choice) => choice.into(),
+		None => ModeChoice::figure_out(ctx.author_id().into(), &ctx).await,
+	};
+
+	let runtype = matches!(runtype_choice, Some(RuntypeChoice::TP));
+
+	let target1: Target = target1.parse()?;
+	let target2: Target = target2.parse()?;
+
+	let (name1, name2) = (target1.to_string(), target2.to_string());
+
+	let player1 = target1.into_player(&ctx).await?;
+	let player2 = target2.into_player(&ctx).await?;
+
+	let records1 = ctx
+		.api_cache()
+		.get_player_records(&player1, mode, runtype, ctx.gokz_client())
+		.await
+		.or_else(|why| match why {
+			Error::ApiUnavailable { .. } => Err(why),
+			_ => Ok(Vec::new()),
+		})?;
+
+	let records2 = ctx
+		.api_cache()
+		.get_player_records(&player2, mode, runtype, ctx.gokz_client())
+		.await
+		.or_else(|why| match why {
+			Error::ApiUnavailable { .. } => Err(why),
+			_ => Ok(Vec::new()),
+		})?;
+
+	if records1.is_empty() && records2.is_empty() {
+		return Err(Error::NoRecords);
+	}
+
+	let name1 = records1
+		.first()
+		.map_or(name1, |record| record.player_name.clone());
+
+	let name2 = records2
+		.first()
+		.map_or(name2, |record| record.player_name.clone());
+
+	let maps = ctx.maps();
+	let global_maps = maps
+		.iter()
+		.map(|map| (map.id, (map.name.clone(), map.tier as u8)))
+		.collect::<HashMap<_, _>>();
+
+	// Only the first record on every map counts, and only if the map is global.
+	let mut times1 = HashMap::new();
+	let mut points1 = 0;
+	for record in &records1 {
+		if global_maps.contains_key(&record.map_id) && !times1.contains_key(&record.map_id) {
+			times1.insert(record.map_id, record.time);
+			points1 += record.points;
+		}
+	}
+
+	let mut times2 = HashMap::new();
+	let mut points2 = 0;
+	for record in &records2 {
+		if global_maps.contains_key(&record.map_id) && !times2.contains_key(&record.map_id) {
+			times2.insert(record.map_id, record.time);
+			points2 += record.points;
+		}
+	}
+
+	// tier -> (wins for player 1, wins for player 2)
+	let mut wins = BTreeMap::<u8, (u32, u32)>::new();
+	let mut both = Vec::new();
+	let mut only1 = Vec::new();
+	let mut only2 = Vec::new();
+
+	for (map_id, (map_name, tier)) in &global_maps {
+		match (times1.get(map_id), times2.get(map_id)) {
+			(Some(&time1), Some(&time2)) => {
+				let entry = wins.entry(*tier).or_default();
+				if time1 < time2 {
+					entry.0 += 1;
+				} else if time2 < time1 {
+					entry.1 += 1;
+				}
+
+				both.push((map_name.as_str(), *tier, time1, time2));
+			}
+			(Some(_), None) => only1.push(format!("{map_name} (T{tier})")),
+			(None, Some(_)) => only2.push(format!("{map_name} (T{tier})")),
+			(None, None) => {}
+		}
+	}
+
+	both.sort_unstable_by(|a, b| a.0.cmp(b.0));
+	only1.sort_unstable();
+	only2.sort_unstable();
+
+	let (total_wins1, total_wins2) = wins
+		.values()
+		.fold((0, 0), |(total1, total2), (wins1, wins2)| (total1 + wins1, total2 + wins2));
+
+	let tier_stats = wins
+		.iter()
+		.map(|(tier, (wins1, wins2))| format!("T{tier} ⌠ {wins1:>4} - {wins2:<4} ⌡"))
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	let points_diff = points1 as i64 - points2 as i64;
+
+	let title =
+		format!("[{} {}] {name1} vs. {name2}", mode.short(), if runtype { "TP" } else { "PRO" });
+
+	let mut template = CreateEmbed::default();
+	template
+		.color(ctx.color())
+		.title(&title);
+
+	let mut summary = template.clone();
+	summary.description(format!(
+		r#"
+**{name1}**: {points1} points, {} maps
+**{name2}**: {points2} points, {} maps
+Points difference: **{}{}**
+──────────────────────────────────────────
+```
+      [{}]   [{}]
+Total ⌠ {total_wins1:>4} - {total_wins2:<4} ⌡
+{tier_stats}
+```
+		"#,
+		times1.len(),
+		times2.len(),
+		if points_diff > 0 { "+" } else { "" },
+		points_diff,
+		name1
+			.chars()
+			.take(4)
+			.collect::<String>(),
+		name2
+			.chars()
+			.take(4)
+			.collect::<String>(),
+	));
+
+	let mut embeds = vec![summary];
+
+	// How many maps per page
+	let chunk_size = 10;
+
+	for maps in both.chunks(chunk_size) {
+		let mut temp = template.clone();
+		temp.description("Maps you both finished");
+
+		for (map_name, tier, time1, time2) in maps {
+			let (winner, diff) =
+				if time1 <= time2 { (&name1, time2 - time1) } else { (&name2, time1 - time2) };
+
+			temp.field(
+				format!("{map_name} (T{tier})"),
+				format!(
+					"> {}: {}\n> {}: {}\n> {winner} +{}",
+					name1,
+					time::format(*time1),
+					name2,
+					time::format(*time2),
+					time::format(diff),
+				),
+				true,
+			);
+		}
+
+		embeds.push(temp);
+	}
+
+	for (name, maps) in [(&name1, &only1), (&name2, &only2)] {
+		for maps in maps.chunks(chunk_size * 2) {
+			let mut temp = template.clone();
+			temp.description(format!("Maps only {name} finished\n\n{}", maps.join("\n")));
+			embeds.push(temp);
+		}
+	}
+
+	let max_pages = embeds.len();
+	for (page_idx, embed) in embeds.iter_mut().enumerate() {
+		embed.footer(|footer| {
+			footer
+				.text(format!("{} | Page {} / {}", ctx.schnose(), page_idx + 1, max_pages))
+				.icon_url(ctx.icon_url())
+		});
+	}
+
+	match embeds.len() {
+		0 => unreachable!(),
+		1 => {
+			ctx.send(|reply| {
+				reply.embed(|embed| {
+					*embed = embeds.remove(0);
+					embed
+				})
+			})
+			.await?;
+		}
+		_ => pagination::paginate(&ctx, embeds).await?,
+	}
+
+	Ok(())
+}
@@ -0,0 +1,286 @@
+use {
+	super::{
+		autocomplete,
+		custom_params::{ModeChoice, RuntypeChoice, TierChoice},
+	},
+	crate::{
+		competitions,
+		error::{Error, Result},
+		state::{Context, StateContainer},
+		utils,
+	},
+	chrono::{DateTime, NaiveDateTime, TimeZone, Utc},
+	poise::serenity_prelude::Channel,
+	rand::Rng,
+};
+
+/// Community competitions on a single map.
+///
+/// Server admins can create a competition on a map for a certain period of time. When it starts, \
+/// the bot saves the personal bests of every member of this server who verified their SteamID \
+/// with `/verify`; members who verify later join automatically. Every few minutes it checks \
+/// whether anyone set a new time on the map, updates the standings message in the \
+/// competition's channel, and announces the winners once the competition is over. Only times \
+/// set during the competition count.
+///
+/// - `/competition create`: create a new competition (requires `Manage Server`)
+/// - `/competition end`: end the current competition early and announce the winners (requires \
+///   `Manage Server`)
+/// - `/competition cancel`: delete the current competition without announcing any winners \
+///   (requires `Manage Server`)
+/// - `/competition standings`: show the current standings
+#[poise::command(
+	slash_command,
+	guild_only,
+	on_error = "Error::handle",
+	subcommands("create", "end", "cancel", "standings")
+)]
+pub async fn competition(_ctx: Context<'_>) -> Result<()> {
+	Ok(())
+}
+
+/// Create a new competition.
+///
+/// Every server can only have one competition at a time. You are required to specify a \
+/// `channel` to post the standings in and an `end` time. You may also specify the following \
+/// options:
+///
+/// - `map`: the map to compete on. If you don't specify this, the bot will pick a random map, \
+///   just like `/random`.
+/// - `tier`: only pick random maps of this tier. This is ignored if you specify a `map`.
+/// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
+/// - `runtype`: `TP` / `PRO`
+///   - If you don't specify this, the bot will default to `PRO`.
+/// - `start`: when the competition starts, e.g. `2023-04-01 18:00` (UTC). If you don't specify \
+///   this, the competition starts right away.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(
+	slash_command,
+	guild_only,
+	ephemeral,
+	required_permissions = "MANAGE_GUILD",
+	on_error = "Error::handle"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn create(
+	ctx: Context<'_>,
+
+	#[description = "The channel to post the standings in."] channel: Channel,
+
+	#[description = "When the competition ends, e.g. `2023-04-08 18:00` (UTC)."] end: String,
+
+	#[description = "Choose a map"]
+	#[rename = "map"]
+	#[autocomplete = "autocomplete::map_name"]
+	map_choice: Option<String>,
+
+	#[description = "Only pick random maps of this tier."]
+	#[rename = "tier"]
+	tier_choice: Option<TierChoice>,
+
+	#[description = "KZT/SKZ/VNL"]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "TP/PRO"]
+	#[rename = "runtype"]
+	runtype_choice: Option<RuntypeChoice>,
+
+	#[description = "When the competition starts, e.g. `2023-04-01 18:00` (UTC)."] start: Option<
+		String,
+	>,
+) -> Result<()> {
+	ctx.defer().await?;
+
+	let guild_id = ctx
+		.guild_id()
+		.ok_or(Error::Custom(String::from("This command only works on servers.")))?;
+
+	if competitions::fetch_active(*guild_id.as_u64(), ctx.db())
+		.await?
+		.is_some()
+	{
+		return Err(Error::CompetitionAlreadyRunning);
+	}
+
+	let mode = match mode_choice {
+		Some(choice) => choice.into(),
+		None => ModeChoice::figure_out(ctx.author_id().into(), &ctx).await,
+	};
+
+	let runtype = matches!(runtype_choice, Some(RuntypeChoice::TP));
+
+	let now = Utc::now();
+	let starts_at = match start {
+		None => now,
+		Some(start) => parse_time(&start)?,
+	};
+	let ends_at = parse_time(&end)?;
+
+	if ends_at <= starts_at || ends_at <= now {
+		return Err(Error::Custom(String::from(
+			"A competition has to end after it starts, and in the future.",
+		)));
+	}
+
+	let map = match map_choice {
+		Some(map_choice) => {
+			let map = ctx.get_map(map_choice)?;
+			ctx.recent_maps()
+				.remember(ctx.author_id(), &map.name);
+
+			map
+		}
+		None => {
+			let maps = ctx.maps();
+			let mut filtered_maps = maps
+				.iter()
+				.filter(|map| tier_choice.map_or(true, |tier| map.tier as u8 == tier as u8))
+				.filter(|map| utils::has_filter(map, mode))
+				.collect::<Vec<_>>();
+
+			if filtered_maps.is_empty() {
+				return Err(Error::Custom(String::from("No maps match these filters.")));
+			}
+
+			let rng = rand::thread_rng().gen_range(0..filtered_maps.len());
+
+			filtered_maps.remove(rng).clone()
+		}
+	};
+
+	let competition = competitions::create(
+		*guild_id.as_u64(),
+		*channel.id().as_u64(),
+		&map.name,
+		mode,
+		runtype,
+		starts_at,
+		ends_at,
+		ctx.db(),
+	)
+	.await?;
+
+	ctx.say(format!(
+		"Created a competition on **{}** ({} {}) from <t:{}:f> until <t:{}:f>! The standings will \
+		 be posted in <#{}> once it starts.",
+		competition.map_name,
+		mode.short(),
+		if runtype { "TP" } else { "PRO" },
+		starts_at.timestamp(),
+		ends_at.timestamp(),
+		competition.channel_id,
+	))
+	.await?;
+
+	Ok(())
+}
+
+/// End the current competition early and announce the winners.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(
+	slash_command,
+	guild_only,
+	ephemeral,
+	required_permissions = "MANAGE_GUILD",
+	on_error = "Error::handle"
+)]
+pub async fn end(ctx: Context<'_>) -> Result<()> {
+	ctx.defer().await?;
+
+	let competition = active_competition(&ctx).await?;
+
+	if !competition.started {
+		return Err(Error::Custom(String::from(
+			"This competition hasn't started yet. Use `/competition cancel` to delete it instead.",
+		)));
+	}
+
+	competitions::end_now(competition.id, ctx.db()).await?;
+
+	ctx.say(format!(
+		"The competition on **{}** is over! The winners will be announced in <#{}> shortly.",
+		competition.map_name, competition.channel_id
+	))
+	.await?;
+
+	Ok(())
+}
+
+/// Delete the current competition without announcing any winners.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(
+	slash_command,
+	guild_only,
+	ephemeral,
+	required_permissions = "MANAGE_GUILD",
+	on_error = "Error::handle"
+)]
+pub async fn cancel(ctx: Context<'_>) -> Result<()> {
+	ctx.defer().await?;
+
+	let competition = active_competition(&ctx).await?;
+
+	competitions::cancel(competition.id, ctx.db()).await?;
+
+	ctx.say(format!("Cancelled the competition on **{}**.", competition.map_name))
+		.await?;
+
+	Ok(())
+}
+
+/// Show the standings of the current competition.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, guild_only, ephemeral, on_error = "Error::handle")]
+pub async fn standings(ctx: Context<'_>) -> Result<()> {
+	ctx.defer_reply().await?;
+
+	let competition = active_competition(&ctx).await?;
+
+	if !competition.started {
+		ctx.say(format!(
+			"The competition on **{}** starts <t:{}:R>.",
+			competition.map_name,
+			competition.starts_at.timestamp()
+		))
+		.await?;
+
+		return Ok(());
+	}
+
+	let entries = competitions::fetch_entries(competition.id, ctx.db()).await?;
+	let embed = competitions::standings_embed(&competition, &entries, false, ctx.data());
+
+	ctx.send(|reply| {
+		reply.embed(|e| {
+			*e = embed;
+			e
+		})
+	})
+	.await?;
+
+	Ok(())
+}
+
+async fn active_competition(ctx: &Context<'_>) -> Result<competitions::Competition> {
+	let guild_id = ctx
+		.guild_id()
+		.ok_or(Error::Custom(String::from("This command only works on servers.")))?;
+
+	competitions::fetch_active(*guild_id.as_u64(), ctx.db())
+		.await?
+		.ok_or(Error::NoActiveCompetition)
+}
+
+/// Parses `YYYY-MM-DD HH:MM` as UTC.
+fn parse_time(input: &str) -> Result<DateTime<Utc>> {
+	NaiveDateTime::parse_from_str(input.trim(), "%Y-%m-%d %H:%M")
+		.map(|time| Utc.from_utc_datetime(&time))
+		.map_err(|_| Error::InvalidDate {
+			input: input.to_owned(),
+			expected: "`YYYY-MM-DD HH:MM` (UTC)",
+		})
+}
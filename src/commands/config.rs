@@ -0,0 +1,260 @@
+use {
+	super::custom_params::{BoolChoice, DBModeChoice},
+	crate::{
+		database,
+		error::{Error, Result},
+		state::{Context, StateContainer},
+	},
+	gokz_rs::Mode,
+	num_format::Locale,
+	poise::serenity_prelude::Channel,
+	sqlx::QueryBuilder,
+};
+
+/// Change the bot's settings for this server.
+///
+/// This command lets server admins change how the bot behaves on their server. You need the \
+/// `Manage Server` permission to use it. Every option you leave out will keep its current value \
+/// and if you don't specify any options, the bot will just show you the current settings. You \
+/// may specify the following options:
+///
+/// - `mode`: `None` / `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - The mode that commands will use if a user did not specify one and has no mode preference \
+///     saved with `/mode`.
+/// - `ephemeral`: whether the bot's replies should only be visible to the user who used a command.
+/// - `locale`: the locale used for formatting numbers, e.g. `en` or `de`.
+/// - `wr_feed_channel`: a channel to post all new world records in. See `/wrfeed` for filters.
+/// - `pb_feed_channel`: a channel to post PB notifications in. See `/notifications`.
+/// - `clear_wr_feed_channel` / `clear_pb_feed_channel`: set this to `Yes` to stop using the \
+///   respective channel.
+/// - `reset`: set this to `Yes` to reset all settings.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(
+	slash_command,
+	guild_only,
+	ephemeral,
+	required_permissions = "MANAGE_GUILD",
+	default_member_permissions = "MANAGE_GUILD",
+	on_error = "Error::handle"
+)]
+pub async fn config(
+	ctx: Context<'_>,
+
+	#[description = "Default mode for this server."]
+	#[rename = "mode"]
+	mode_choice: Option<DBModeChoice>,
+
+	#[description = "Only show replies to the user who used a command."]
+	#[rename = "ephemeral"]
+	ephemeral_choice: Option<BoolChoice>,
+
+	#[description = "Locale used for formatting numbers, e.g. `en` or `de`."] locale: Option<
+		String,
+	>,
+
+	#[description = "Channel to post new world records in."] wr_feed_channel: Option<Channel>,
+
+	#[description = "Channel to post PB notifications in."] pb_feed_channel: Option<Channel>,
+
+	#[description = "Stop posting new world records."]
+	#[rename = "clear_wr_feed_channel"]
+	clear_wr_feed_channel_choice: Option<BoolChoice>,
+
+	#[description = "Stop posting PB notifications in a server channel."]
+	#[rename = "clear_pb_feed_channel"]
+	clear_pb_feed_channel_choice: Option<BoolChoice>,
+
+	#[description = "Reset all settings."]
+	#[rename = "reset"]
+	reset_choice: Option<BoolChoice>,
+) -> Result<()> {
+	ctx.defer().await?;
+
+	let guild_id = ctx
+		.guild_id()
+		.ok_or(Error::Custom(String::from("This command only works on servers.")))?;
+
+	if reset_choice.map_or(false, bool::from) {
+		if let Some(channel_id) = ctx
+			.fetch_guild()
+			.await
+			.and_then(|guild| guild.wr_feed_channel_id)
+		{
+			unsubscribe_wr_feed(&ctx, channel_id).await?;
+		}
+
+		sqlx::query("DELETE FROM guilds WHERE guild_id = $1")
+			.bind(*guild_id.as_u64() as i64)
+			.execute(ctx.db())
+			.await?;
+
+		ctx.guild_settings()
+			.remove(*guild_id.as_u64());
+
+		ctx.say("Successfully reset all settings for this server.")
+			.await?;
+
+		return Ok(());
+	}
+
+	if let Some(locale) = &locale {
+		if Locale::from_name(locale).is_err() {
+			return Err(Error::Custom(format!("`{locale}` is not a valid locale.")));
+		}
+	}
+
+	let mut guild = ctx
+		.fetch_guild()
+		.await
+		.unwrap_or(database::Guild {
+			guild_id: *guild_id.as_u64(),
+			mode: None,
+			ephemeral: None,
+			locale: None,
+			wr_feed_channel_id: None,
+			pb_feed_channel_id: None,
+		});
+
+	if let Some(mode_choice) = mode_choice {
+		guild.mode = mode_choice.into();
+	}
+
+	if let Some(ephemeral_choice) = ephemeral_choice {
+		guild.ephemeral = Some(ephemeral_choice.into());
+	}
+
+	if locale.is_some() {
+		guild.locale = locale;
+	}
+
+	let old_wr_feed_channel_id = guild.wr_feed_channel_id;
+
+	if clear_wr_feed_channel_choice.map_or(false, bool::from) {
+		guild.wr_feed_channel_id = None;
+	}
+
+	if clear_pb_feed_channel_choice.map_or(false, bool::from) {
+		guild.pb_feed_channel_id = None;
+	}
+
+	if let Some(channel) = &wr_feed_channel {
+		guild.wr_feed_channel_id = Some(*channel.id().as_u64());
+
+		// Subscribe the channel to all world records, unless it already has a subscription with
+		// custom filters.
+		sqlx::query(
+			r#"
+			INSERT INTO wr_feed_subscriptions (channel_id, guild_id)
+			VALUES ($1, $2)
+			ON CONFLICT (channel_id) DO NOTHING
+			"#,
+		)
+		.bind(*channel.id().as_u64() as i64)
+		.bind(*guild_id.as_u64() as i64)
+		.execute(ctx.db())
+		.await?;
+	}
+
+	if let Some(channel) = &pb_feed_channel {
+		guild.pb_feed_channel_id = Some(*channel.id().as_u64());
+	}
+
+	let mut query = QueryBuilder::new(
+		r#"
+		INSERT INTO guilds
+		    (guild_id, mode, ephemeral, locale, wr_feed_channel_id, pb_feed_channel_id)
+		"#,
+	);
+
+	query
+		.push_values([&guild], |mut query, guild| {
+			query
+				.push_bind(guild.guild_id as i64)
+				.push_bind(guild.mode.map(|mode| mode as u8 as i16))
+				.push_bind(guild.ephemeral)
+				.push_bind(guild.locale.clone())
+				.push_bind(
+					guild
+						.wr_feed_channel_id
+						.map(|id| id as i64),
+				)
+				.push_bind(
+					guild
+						.pb_feed_channel_id
+						.map(|id| id as i64),
+				);
+		})
+		.push(
+			r#"
+			ON CONFLICT (guild_id) DO UPDATE SET
+			    mode = EXCLUDED.mode,
+			    ephemeral = EXCLUDED.ephemeral,
+			    locale = EXCLUDED.locale,
+			    wr_feed_channel_id = EXCLUDED.wr_feed_channel_id,
+			    pb_feed_channel_id = EXCLUDED.pb_feed_channel_id
+			"#,
+		);
+
+	query.build().execute(ctx.db()).await?;
+
+	// The old channel was only subscribed because it was the server's feed channel.
+	if let Some(channel_id) = old_wr_feed_channel_id {
+		if guild.wr_feed_channel_id != Some(channel_id) {
+			unsubscribe_wr_feed(&ctx, channel_id).await?;
+		}
+	}
+
+	ctx.guild_settings().set(guild.clone());
+
+	let channel = |channel_id: Option<u64>| {
+		channel_id.map_or_else(|| String::from("NULL"), |channel_id| format!("<#{channel_id}>"))
+	};
+
+	let description = format!(
+		r#"
+> `mode`: `{}`
+> `ephemeral`: `{}`
+> `locale`: `{}`
+> `wr_feed_channel`: {}
+> `pb_feed_channel`: {}
+		"#,
+		guild
+			.mode
+			.map_or_else(|| String::from("NULL"), |mode: Mode| mode.short()),
+		guild
+			.ephemeral
+			.map_or_else(|| String::from("NULL"), |ephemeral| ephemeral.to_string()),
+		guild
+			.locale
+			.as_deref()
+			.unwrap_or("NULL"),
+		channel(guild.wr_feed_channel_id),
+		channel(guild.pb_feed_channel_id),
+	);
+
+	ctx.send(|reply| {
+		reply.embed(|embed| {
+			embed
+				.color(ctx.color())
+				.title("Server settings")
+				.description(description)
+				.footer(|footer| {
+					footer
+						.text(ctx.schnose())
+						.icon_url(ctx.icon_url())
+				})
+		})
+	})
+	.await?;
+
+	Ok(())
+}
+
+async fn unsubscribe_wr_feed(ctx: &Context<'_>, channel_id: u64) -> Result<()> {
+	sqlx::query("DELETE FROM wr_feed_subscriptions WHERE channel_id = $1")
+		.bind(channel_id as i64)
+		.execute(ctx.db())
+		.await?;
+
+	Ok(())
+}
@@ -0,0 +1,16 @@
+use poise::ChoiceParameter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum LeaderboardMetric {
+	#[name = "Points"]
+	Points,
+
+	#[name = "Completions"]
+	Completions,
+
+	#[name = "World Records"]
+	WRs,
+
+	#[name = "Average Points"]
+	AveragePoints,
+}
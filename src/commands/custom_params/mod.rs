@@ -12,3 +12,9 @@ pub use db_mode_choice::DBModeChoice;
 
 mod tier_choice;
 pub use tier_choice::TierChoice;
+
+mod notification_choice;
+pub use notification_choice::NotificationChoice;
+
+mod leaderboard_metric;
+pub use leaderboard_metric::LeaderboardMetric;
@@ -21,10 +21,23 @@ pub enum ModeChoice {
 
 impl ModeChoice {
 	/// Figure out a mode to use if the user did not specify one.
+	///
+	/// This will try the user's mode preference first, then the server's default mode and fall
+	/// back to [`Mode::KZTimer`] if neither is set.
 	pub async fn figure_out(target: Target, ctx: &Context<'_>) -> Mode {
-		ctx.fetch_user(target.clone())
+		// If Steam can't tell us who `target` is, the command will say so once it looks up the
+		// player, so we just move on to the server's default here.
+		if let Ok(Some(mode)) = ctx
+			.fetch_user(target.clone())
 			.await
-			.and_then(|user| user.mode)
+			.map(|user| user.and_then(|user| user.mode))
+		{
+			return mode;
+		}
+
+		ctx.fetch_guild()
+			.await
+			.and_then(|guild| guild.mode)
 			.unwrap_or(Mode::KZTimer)
 	}
 }
@@ -0,0 +1,10 @@
+use poise::ChoiceParameter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum NotificationChoice {
+	#[name = "DM"]
+	DM,
+
+	#[name = "This Channel"]
+	Channel,
+}
@@ -30,10 +30,18 @@ pub async fn db(
 
 	let user_id = *ctx.author().id.as_u64();
 
-	let database::User { name, discord_id, steam_id, mode } =
-		ctx.fetch_user_by_id(user_id)
-			.await
-			.ok_or(Error::UserNotInDatabase { user: Target::None { user_id } })?;
+	let database::User {
+		name,
+		discord_id,
+		steam_id,
+		mode,
+		pb_notifications,
+		verified,
+		hide_unfiltered_maps,
+		..
+	} = ctx.fetch_user_by_id(user_id)
+		.await
+		.ok_or(Error::UserNotInDatabase { user: Target::None { user_id } })?;
 
 	let steam_id = steam_id
 		.map(|steam_id| steam_id.to_string())
@@ -49,6 +57,9 @@ pub async fn db(
 > `discord_id`: `{discord_id}`
 > `steam_id`: `{steam_id}`
 > `mode`: `{mode}`
+> `pb_notifications`: `{pb_notifications}`
+> `verified`: `{verified}`
+> `hide_unfiltered_maps`: `{hide_unfiltered_maps}`
 		"#
 	);
 
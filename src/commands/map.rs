@@ -4,6 +4,8 @@ use {
 		error::{Error, Result},
 		state::{Context, StateContainer},
 	},
+	poise::serenity_prelude::CreateEmbed,
+	schnosebot::global_map::GlobalMap,
 };
 
 /// Get detailed information on a map.
@@ -24,10 +26,27 @@ pub async fn map(
 	#[autocomplete = "autocomplete::map_name"]
 	map_choice: String,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let map = ctx.get_map(map_choice.clone())?;
+	ctx.recent_maps()
+		.remember(ctx.author_id(), &map.name);
 
+	let embed = embed(&map, ctx.color());
+
+	ctx.send(|reply| {
+		reply.embed(|e| {
+			*e = embed;
+			e
+		})
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// Tier, mapper, bonuses and filters of a map.
+pub fn embed(map: &GlobalMap, color: (u8, u8, u8)) -> CreateEmbed {
 	let mapper = match &map.mapper_steam_id {
 		None => map.mapper_name.clone(),
 		Some(steam_id) => format!(
@@ -41,34 +60,30 @@ pub async fn map(
 	let skz_filer = if map.skz { "✅" } else { "❌" };
 	let vnl_filer = if map.vnl { "✅" } else { "❌" };
 
-	ctx.send(|reply| {
-		reply.embed(|embed| {
-			embed
-				.color(ctx.color())
-				.title(&map.name)
-				.url(&map.kzgo_link())
-				.thumbnail(&map.thumbnail())
-				.description(format!(
-					r#"
+	let mut embed = CreateEmbed::default();
+	embed
+		.color(color)
+		.title(&map.name)
+		.url(&map.kzgo_link())
+		.thumbnail(&map.thumbnail())
+		.description(format!(
+			r#"
 🡆 Tier: {} ({})
 🡆 Mapper(s): {}
 🡆 Bonuses: {}
 🡆 Last Updated: {}
 
 🡆 Filters:
-					"#,
-					map.tier as u8,
-					map.tier,
-					mapper,
-					map.courses.len() - 1,
-					map.updated_on.format("%d/%m/%Y"),
-				))
-				.field("KZT", kzt_filer, true)
-				.field("SKZ", skz_filer, true)
-				.field("VNL", vnl_filer, true)
-		})
-	})
-	.await?;
+			"#,
+			map.tier as u8,
+			map.tier,
+			mapper,
+			map.courses.len() - 1,
+			map.updated_on.format("%d/%m/%Y"),
+		))
+		.field("KZT", kzt_filer, true)
+		.field("SKZ", skz_filer, true)
+		.field("VNL", vnl_filer, true);
 
-	Ok(())
+	embed
 }
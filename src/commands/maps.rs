@@ -0,0 +1,331 @@
+use {
+	super::{
+		custom_params::{ModeChoice, TierChoice},
+		map,
+		pagination::{self, Action, Button},
+	},
+	crate::{
+		error::{Error, Result},
+		state::{Context, StateContainer},
+		utils,
+	},
+	chrono::NaiveDate,
+	gokz_rs::Mode,
+	poise::serenity_prelude::CreateEmbed,
+	rand::Rng,
+	schnosebot::global_map::GlobalMap,
+};
+
+/// How many maps are listed on a single page.
+const MAPS_PER_PAGE: usize = 15;
+
+/// Search the global map pool.
+///
+/// This command will list every global map that matches all of the filters you specify. You can \
+/// flip through the results with the arrow buttons, or let the bot pick a random map out of them \
+/// with the 🎲 button. You may specify the following options:
+///
+/// - `name`: only maps whose name contains this, e.g. `bhop`
+/// - `min_tier` / `max_tier`: only maps in this range of tiers
+/// - `mapper`: only maps made by someone whose name contains this
+/// - `mode`: only maps with a filter for this mode
+/// - `bonuses`: only maps with at least this many bonuses
+/// - `updated_since`: only maps that have been updated since this date, e.g. `2023-01-01`
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
+#[allow(clippy::too_many_arguments)]
+pub async fn maps(
+	ctx: Context<'_>,
+
+	#[description = "Only maps whose name contains this."] name: Option<String>,
+
+	#[description = "Lowest tier"]
+	#[rename = "min_tier"]
+	min_tier_choice: Option<TierChoice>,
+
+	#[description = "Highest tier"]
+	#[rename = "max_tier"]
+	max_tier_choice: Option<TierChoice>,
+
+	#[description = "Only maps made by someone whose name contains this."] mapper: Option<String>,
+
+	#[description = "Only maps with a filter for this mode."]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "Only maps with at least this many bonuses."] bonuses: Option<u8>,
+
+	#[description = "Only maps updated since this date, e.g. `2023-01-01`."] updated_since: Option<
+		String,
+	>,
+) -> Result<()> {
+	ctx.defer_reply().await?;
+
+	let min_tier = min_tier_choice.map_or(1, |tier| tier as u8);
+	let max_tier = max_tier_choice.map_or(7, |tier| tier as u8);
+
+	if min_tier > max_tier {
+		return Err(Error::Custom(format!(
+			"`min_tier` (T{min_tier}) can't be higher than `max_tier` (T{max_tier})."
+		)));
+	}
+
+	let filters = MapFilters {
+		name: name.map(|name| name.to_lowercase()),
+		min_tier,
+		max_tier,
+		mapper: mapper.map(|mapper| mapper.to_lowercase()),
+		mode: mode_choice.map(Mode::from),
+		bonuses: bonuses.unwrap_or(0) as usize,
+		updated_since: updated_since
+			.as_deref()
+			.map(parse_date)
+			.transpose()?,
+	};
+
+	let maps = ctx
+		.maps()
+		.iter()
+		.filter(|map| filters.matches(&MapSummary::from(*map)))
+		.cloned()
+		.collect::<Vec<_>>();
+
+	if maps.is_empty() {
+		return Err(Error::Custom(String::from("No maps match these filters.")));
+	}
+
+	let page_count = (maps.len() as f64 / MAPS_PER_PAGE as f64).ceil() as usize;
+	let embeds = maps
+		.chunks(MAPS_PER_PAGE)
+		.enumerate()
+		.map(|(page_idx, page)| {
+			let mut embed = CreateEmbed::default();
+			embed
+				.color(ctx.color())
+				.title(format!("{} maps", maps.len()))
+				.description(
+					page.iter()
+						.map(|map| format!("[{}]({})", utils::describe_map(map), map.kzgo_link()))
+						.collect::<Vec<_>>()
+						.join("\n"),
+				)
+				.footer(|f| {
+					f.text(format!("Page {} / {page_count}", page_idx + 1))
+						.icon_url(ctx.icon_url())
+				});
+
+			embed
+		})
+		.collect::<Vec<_>>();
+
+	let random = Button { name: "random", label: '🎲' };
+
+	pagination::paginate_with_buttons(&ctx, embeds, &[random], |_, _| {
+		let rng = rand::thread_rng().gen_range(0..maps.len());
+
+		Action::Post(map::embed(&maps[rng], ctx.color()))
+	})
+	.await
+}
+
+/// Everything a map has to match to show up in `/maps`. Names are expected to be lowercase.
+#[derive(Debug, Clone)]
+struct MapFilters {
+	name: Option<String>,
+	min_tier: u8,
+	max_tier: u8,
+	mapper: Option<String>,
+	mode: Option<Mode>,
+	bonuses: usize,
+	updated_since: Option<NaiveDate>,
+}
+
+impl MapFilters {
+	fn matches(&self, map: &MapSummary<'_>) -> bool {
+		let name_matches = self
+			.name
+			.as_ref()
+			.map_or(true, |name| map.name.to_lowercase().contains(name));
+
+		let mapper_matches = self
+			.mapper
+			.as_ref()
+			.map_or(true, |mapper| {
+				map.mapper
+					.to_lowercase()
+					.contains(mapper)
+			});
+
+		let has_filter = self
+			.mode
+			.map_or(true, |mode| map.filters.contains(&mode));
+
+		let updated_since = self
+			.updated_since
+			.map_or(true, |since| map.updated_on >= since);
+
+		name_matches
+			&& (self.min_tier..=self.max_tier).contains(&map.tier)
+			&& mapper_matches
+			&& has_filter
+			&& map.bonuses >= self.bonuses
+			&& updated_since
+	}
+}
+
+/// The parts of a [`GlobalMap`] that [`MapFilters`] care about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MapSummary<'a> {
+	name: &'a str,
+	tier: u8,
+	mapper: &'a str,
+	filters: Vec<Mode>,
+	bonuses: usize,
+	updated_on: NaiveDate,
+}
+
+impl<'a> From<&'a GlobalMap> for MapSummary<'a> {
+	fn from(map: &'a GlobalMap) -> Self {
+		Self {
+			name: &map.name,
+			tier: map.tier as u8,
+			mapper: &map.mapper_name,
+			filters: [
+				Mode::KZTimer,
+				Mode::SimpleKZ,
+				Mode::Vanilla,
+			]
+			.into_iter()
+			.filter(|mode| utils::has_filter(map, *mode))
+			.collect(),
+			bonuses: map
+				.courses
+				.iter()
+				.filter(|course| course.stage != 0)
+				.count(),
+			updated_on: map.updated_on.date(),
+		}
+	}
+}
+
+/// Parses `YYYY-MM-DD`.
+fn parse_date(input: &str) -> Result<NaiveDate> {
+	NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d").map_err(|_| Error::InvalidDate {
+		input: input.to_owned(),
+		expected: "`YYYY-MM-DD`",
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dates() {
+		assert_eq!(parse_date(" 2023-01-01 "), Ok(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap()));
+		assert_eq!(
+			parse_date("01/01/2023"),
+			Err(Error::InvalidDate {
+				input: String::from("01/01/2023"),
+				expected: "`YYYY-MM-DD`"
+			})
+		);
+	}
+
+	fn filters() -> MapFilters {
+		MapFilters {
+			name: None,
+			min_tier: 1,
+			max_tier: 7,
+			mapper: None,
+			mode: None,
+			bonuses: 0,
+			updated_since: None,
+		}
+	}
+
+	fn lionharder() -> MapSummary<'static> {
+		MapSummary {
+			name: "kz_lionHARDer",
+			tier: 6,
+			mapper: "Chuckles",
+			filters: vec![Mode::KZTimer, Mode::SimpleKZ],
+			bonuses: 2,
+			updated_on: NaiveDate::from_ymd_opt(2023, 3, 15).unwrap(),
+		}
+	}
+
+	#[test]
+	fn no_filters() {
+		assert!(filters().matches(&lionharder()));
+	}
+
+	#[test]
+	fn names() {
+		let map = lionharder();
+
+		assert!(MapFilters {
+			name: Some(String::from("harder")),
+			..filters()
+		}
+		.matches(&map));
+		assert!(!MapFilters {
+			name: Some(String::from("erratum")),
+			..filters()
+		}
+		.matches(&map));
+		assert!(MapFilters {
+			mapper: Some(String::from("chuck")),
+			..filters()
+		}
+		.matches(&map));
+		assert!(!MapFilters {
+			mapper: Some(String::from("alpha")),
+			..filters()
+		}
+		.matches(&map));
+	}
+
+	#[test]
+	fn tiers() {
+		let map = lionharder();
+
+		assert!(MapFilters { min_tier: 6, max_tier: 6, ..filters() }.matches(&map));
+		assert!(MapFilters { min_tier: 5, max_tier: 7, ..filters() }.matches(&map));
+		assert!(!MapFilters { max_tier: 5, ..filters() }.matches(&map));
+		assert!(!MapFilters { min_tier: 7, ..filters() }.matches(&map));
+	}
+
+	#[test]
+	fn modes_and_bonuses() {
+		let map = lionharder();
+
+		assert!(MapFilters { mode: Some(Mode::SimpleKZ), ..filters() }.matches(&map));
+		assert!(!MapFilters { mode: Some(Mode::Vanilla), ..filters() }.matches(&map));
+		assert!(MapFilters { bonuses: 2, ..filters() }.matches(&map));
+		assert!(!MapFilters { bonuses: 3, ..filters() }.matches(&map));
+	}
+
+	#[test]
+	fn update_dates() {
+		let map = lionharder();
+		let since = |y, m, d| Some(NaiveDate::from_ymd_opt(y, m, d).unwrap());
+
+		assert!(MapFilters {
+			updated_since: since(2023, 1, 1),
+			..filters()
+		}
+		.matches(&map));
+		// The day of the update itself counts.
+		assert!(MapFilters {
+			updated_since: since(2023, 3, 15),
+			..filters()
+		}
+		.matches(&map));
+		assert!(!MapFilters {
+			updated_since: since(2023, 3, 16),
+			..filters()
+		}
+		.matches(&map));
+	}
+}
@@ -8,7 +8,6 @@ use {
 		error::{Error, Result},
 		state::{Context, StateContainer},
 	},
-	gokz_rs::global_api,
 	poise::serenity_prelude::CreateEmbed,
 	schnosebot::time,
 };
@@ -20,9 +19,9 @@ use {
 /// following options:
 ///
 /// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-///     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-///     preference in the database, see `/mode`.
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
 /// - `runtype`: `TP` / `PRO`
 ///   - If you don't specify this, the bot will default to `PRO`.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
@@ -43,9 +42,11 @@ pub async fn maptop(
 	#[rename = "runtype"]
 	runtype_choice: Option<RuntypeChoice>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let map = ctx.get_map(map_choice.clone())?;
+	ctx.recent_maps()
+		.remember(ctx.author_id(), &map.name);
 
 	let mode = match mode_choice {
 		Some(choice) => choice.into(),
@@ -54,9 +55,10 @@ pub async fn maptop(
 
 	let runtype = matches!(runtype_choice, Some(RuntypeChoice::TP));
 
-	let maptop =
-		global_api::get_maptop(map.name.clone().into(), mode, runtype, 0, ctx.gokz_client())
-			.await?;
+	let maptop = ctx
+		.api_cache()
+		.get_maptop(&map.name, mode, runtype, 0, ctx.gokz_client())
+		.await?;
 
 	let mut embeds = Vec::new();
 	let mut temp = CreateEmbed::default();
@@ -17,6 +17,15 @@ pub use btop::btop;
 mod bwr;
 pub use bwr::bwr;
 
+mod compare;
+pub use compare::compare;
+
+mod competition;
+pub use competition::competition;
+
+mod config;
+pub use config::config;
+
 mod db;
 pub use db::db;
 
@@ -29,6 +38,9 @@ pub use invite::invite;
 mod map;
 pub use map::map;
 
+mod maps;
+pub use maps::maps;
+
 mod maptop;
 pub use maptop::maptop;
 
@@ -38,6 +50,9 @@ pub use mode::mode;
 mod nocrouch;
 pub use nocrouch::nocrouch;
 
+mod notifications;
+pub use notifications::notifications;
+
 mod pb;
 pub use pb::pb;
 
@@ -47,6 +62,9 @@ pub use ping::ping;
 mod profile;
 pub use profile::profile;
 
+mod progress;
+pub use progress::progress;
+
 mod random;
 pub use random::random;
 
@@ -56,6 +74,12 @@ pub use recent::recent;
 mod report;
 pub use report::report;
 
+mod serverlb;
+pub use serverlb::serverlb;
+
+mod servertop;
+pub use servertop::servertop;
+
 mod setsteam;
 pub use setsteam::setsteam;
 
@@ -65,5 +89,11 @@ pub use top::top;
 mod unfinished;
 pub use unfinished::unfinished;
 
+mod verify;
+pub use verify::verify;
+
 mod wr;
 pub use wr::wr;
+
+mod wrfeed;
+pub use wrfeed::wrfeed;
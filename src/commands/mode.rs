@@ -1,11 +1,11 @@
 use {
-	super::custom_params::DBModeChoice,
+	super::custom_params::{BoolChoice, DBModeChoice},
 	crate::{
+		database::UserRepository,
 		error::{Error, Result},
 		state::{Context, StateContainer},
 	},
 	gokz_rs::Mode,
-	sqlx::QueryBuilder,
 };
 
 /// Set your mode preference.
@@ -14,6 +14,9 @@ use {
 /// have a `mode` parameter you probably don't want to specify that over and over again. Instead \
 /// you can use this command and the bot will remember your choice in the future. You can also \
 /// clear your preference if you want to.
+///
+/// - `hide_unfiltered`: whether map suggestions should leave out maps that don't have a filter \
+///   for your preferred mode. If you only specify this, your mode preference stays the same.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn mode(
@@ -22,81 +25,207 @@ pub async fn mode(
 	#[description = "None/KZT/SKZ/VNL"]
 	#[rename = "mode"]
 	mode_choice: Option<DBModeChoice>,
+
+	#[description = "Hide maps without a filter for your mode from suggestions."]
+	#[rename = "hide_unfiltered"]
+	hide_unfiltered_choice: Option<BoolChoice>,
 ) -> Result<()> {
 	ctx.defer().await?;
 
-	let mode: Option<Mode> = mode_choice.and_then(|choice| choice.into());
-
 	let (name, id) = (&ctx.author().name, ctx.author_id());
 
-	let table_name = &ctx.config().users_table;
+	let mut messages = Vec::new();
 
-	let updated = match ctx.fetch_user_by_id(id).await {
-		// User already has a database entry -> modify the current one
-		Some(user) => {
-			if user.mode.as_ref() == mode.as_ref() {
-				// The user already has a mode set and tried to set it to the same value.
-				// :tf:
-				ctx.say("<:tf:999383331647012935>")
-					.await?;
-				return Ok(());
+	if let Some(hide_unfiltered_choice) = hide_unfiltered_choice {
+		let hide = bool::from(hide_unfiltered_choice);
+		messages.push(match set_hide_unfiltered_maps(ctx.users(), name, id, hide).await? {
+			Update::Unchanged => String::from("<:tf:999383331647012935>"),
+			Update::Updated | Update::Created if hide => {
+				String::from("Map suggestions will only show maps with a filter for your mode now.")
+			}
+			Update::Updated | Update::Created => {
+				String::from("Map suggestions will show all maps again.")
+			}
+		});
+	}
+
+	// Only touch the mode preference if the user actually asked for it (or gave no options at
+	// all, which clears it).
+	if mode_choice.is_some() || hide_unfiltered_choice.is_none() {
+		let mode: Option<Mode> = mode_choice.and_then(|choice| choice.into());
+
+		messages.push(match (set_mode(ctx.users(), name, id, mode).await?, mode) {
+			// The user tried to set their mode to the value it already has.
+			// :tf:
+			(Update::Unchanged, _) => String::from("<:tf:999383331647012935>"),
+			(Update::Updated | Update::Created, None) => {
+				format!("Successfully cleared Mode for <@{id}>!")
 			}
+			(Update::Updated, Some(mode)) => {
+				format!("Successfully updated Mode for <@{id}>! New Mode: `{mode}`")
+			}
+			(Update::Created, Some(mode)) => {
+				format!("Successfully set Mode `{mode}` for <@{id}>!")
+			}
+		});
+	}
+
+	ctx.say(messages.join("\n")).await?;
 
-			let mut query = QueryBuilder::new(format!(
-				r#"
-				UPDATE {table_name}
-				SET mode =
-				"#
-			));
+	Ok(())
+}
 
-			query
-				.push_bind(mode.map(|mode| mode as u8 as i16))
-				.push(" WHERE discord_id = ")
-				.push_bind(id as i64);
+/// What happened to a user's database entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+	/// Nothing had to be changed.
+	Unchanged,
 
-			query.build().execute(ctx.db()).await?;
+	/// An existing entry was modified.
+	Updated,
 
-			true
-		}
+	/// A new entry was created.
+	Created,
+}
+
+/// Saves `mode` as the mode preference of the user with the given `discord_id`.
+pub async fn set_mode(
+	users: &impl UserRepository,
+	name: &str,
+	discord_id: u64,
+	mode: Option<Mode>,
+) -> Result<Update> {
+	let update = match users.get(discord_id).await? {
+		// User already has a database entry -> modify the current one
+		Some(user) if user.mode == mode => return Ok(Update::Unchanged),
+		Some(_) => Update::Updated,
+
+		// The user does not yet have an entry but told us to clear their current entry.
+		None if mode.is_none() => return Ok(Update::Unchanged),
 
 		// The user does not yet have an entry -> create one
-		None => {
-			let Some(mode) = mode else {
-				// The user does not yet have an entry but told us to clear their current entry.
-				// :tf:
-				ctx.say("<:tf:999383331647012935>").await?;
-				return Ok(());
-			};
-
-			let mut query = QueryBuilder::new(format!(
-				r#"
-				INSERT INTO {table_name}
-				    (name, discord_id, mode)
-				"#
-			));
-
-			query.push_values([(name, id, mode)], |mut query, (name, id, mode)| {
-				query
-					.push_bind(name)
-					.push_bind(id as i64)
-					.push_bind(mode as u8 as i16);
-			});
-
-			query.build().execute(ctx.db()).await?;
-
-			// We did not update an entry, but created one
-			false
-		}
+		None => Update::Created,
 	};
 
-	ctx.say(match mode {
-		None => format!("Successfully cleared Mode for <@{id}>!"),
-		Some(mode) if updated => {
-			format!("Successfully updated Mode for <@{id}>! New Mode: `{mode}`")
-		}
-		Some(mode) => format!("Successfully set Mode `{mode}` for <@{id}>!"),
-	})
-	.await?;
+	users
+		.set_mode(name, discord_id, mode)
+		.await?;
 
-	Ok(())
+	Ok(update)
+}
+
+/// Saves whether map suggestions should hide maps without a filter for the user's mode.
+pub async fn set_hide_unfiltered_maps(
+	users: &impl UserRepository,
+	name: &str,
+	discord_id: u64,
+	hide: bool,
+) -> Result<Update> {
+	let update = match users.get(discord_id).await? {
+		Some(user) if user.hide_unfiltered_maps == hide => return Ok(Update::Unchanged),
+		Some(_) => Update::Updated,
+
+		// Not hiding anything is the default anyway.
+		None if !hide => return Ok(Update::Unchanged),
+		None => Update::Created,
+	};
+
+	users
+		.set_hide_unfiltered_maps(name, discord_id, hide)
+		.await?;
+
+	Ok(update)
+}
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::database::InMemoryUserRepository};
+
+	#[tokio::test]
+	async fn create() {
+		let users = InMemoryUserRepository::default();
+		let update = set_mode(&users, "AlphaKeks", 1, Some(Mode::SimpleKZ)).await;
+
+		assert_eq!(update, Ok(Update::Created));
+		assert_eq!(
+			users
+				.get(1)
+				.await
+				.unwrap()
+				.unwrap()
+				.mode,
+			Some(Mode::SimpleKZ)
+		);
+	}
+
+	#[tokio::test]
+	async fn update() {
+		let users = InMemoryUserRepository::default();
+		set_mode(&users, "AlphaKeks", 1, Some(Mode::SimpleKZ))
+			.await
+			.unwrap();
+
+		let update = set_mode(&users, "AlphaKeks", 1, Some(Mode::KZTimer)).await;
+		assert_eq!(update, Ok(Update::Updated));
+		assert_eq!(
+			users
+				.get(1)
+				.await
+				.unwrap()
+				.unwrap()
+				.mode,
+			Some(Mode::KZTimer)
+		);
+
+		let update = set_mode(&users, "AlphaKeks", 1, None).await;
+		assert_eq!(update, Ok(Update::Updated));
+		assert_eq!(
+			users
+				.get(1)
+				.await
+				.unwrap()
+				.unwrap()
+				.mode,
+			None
+		);
+	}
+
+	#[tokio::test]
+	async fn unchanged() {
+		let users = InMemoryUserRepository::default();
+
+		let update = set_mode(&users, "AlphaKeks", 1, None).await;
+		assert_eq!(update, Ok(Update::Unchanged));
+		assert_eq!(users.get(1).await, Ok(None));
+
+		set_mode(&users, "AlphaKeks", 1, Some(Mode::Vanilla))
+			.await
+			.unwrap();
+
+		let update = set_mode(&users, "AlphaKeks", 1, Some(Mode::Vanilla)).await;
+		assert_eq!(update, Ok(Update::Unchanged));
+	}
+
+	#[tokio::test]
+	async fn hide_unfiltered_maps() {
+		let users = InMemoryUserRepository::default();
+
+		let update = set_hide_unfiltered_maps(&users, "AlphaKeks", 1, false).await;
+		assert_eq!(update, Ok(Update::Unchanged));
+		assert_eq!(users.get(1).await, Ok(None));
+
+		let update = set_hide_unfiltered_maps(&users, "AlphaKeks", 1, true).await;
+		assert_eq!(update, Ok(Update::Created));
+
+		set_mode(&users, "AlphaKeks", 1, Some(Mode::SimpleKZ))
+			.await
+			.unwrap();
+
+		let user = users.get(1).await.unwrap().unwrap();
+		assert!(user.hide_unfiltered_maps);
+		assert_eq!(user.mode, Some(Mode::SimpleKZ));
+
+		let update = set_hide_unfiltered_maps(&users, "AlphaKeks", 1, false).await;
+		assert_eq!(update, Ok(Update::Updated));
+	}
 }
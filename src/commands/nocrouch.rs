@@ -1,6 +1,6 @@
 use crate::{
 	error::{Error, Result},
-	state::Context,
+	state::{Context, StateContainer},
 };
 
 /// Approximate a nocrouch jump's potential distance.
@@ -23,7 +23,7 @@ pub async fn nocrouch(
 	#[description = "The distance of your jump"] distance: f64,
 	#[description = "The max speed of your jump"] max: f64,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let potential_distance = (max / 128f64).mul_add(4f64, distance);
 
@@ -0,0 +1,106 @@
+use {
+	super::custom_params::{BoolChoice, NotificationChoice},
+	crate::{
+		database::UserRepository,
+		error::{Error, Result},
+		pb_notifications,
+		state::{Context, StateContainer},
+		target::Target,
+	},
+	gokz_rs::schnose_api,
+};
+
+/// Get notified when you set a new personal best.
+///
+/// This command will make the bot check your most recent runs every few minutes and notify you \
+/// whenever you set a new personal best. The notification includes your old time, your new time, \
+/// the improvement and your new placement. For this to work, you need to have your `SteamID` \
+/// saved in the bot's database (see `/setsteam`) and verified (see `/verify`). You may specify \
+/// where you want to be notified:
+///
+/// - `destination`: `DM` / `This Channel`
+///   - If you don't specify this, the bot will send you a DM. If you choose `This Channel`, the \
+///     bot will ping you in the channel you used this command in, or in the server's PB channel \
+///     if the server admins have set one with `/config`.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
+pub async fn notifications(
+	ctx: Context<'_>,
+
+	#[description = "Do you want to get notified about new PBs?"]
+	#[rename = "enabled"]
+	enabled_choice: BoolChoice,
+
+	#[description = "DM/This Channel"]
+	#[rename = "destination"]
+	destination_choice: Option<NotificationChoice>,
+) -> Result<()> {
+	ctx.defer().await?;
+
+	let user_id = ctx.author_id();
+
+	let user = ctx
+		.fetch_user_by_id(user_id)
+		.await
+		.ok_or(Error::UserNotInDatabase { user: Target::None { user_id } })?;
+
+	if !bool::from(enabled_choice) {
+		ctx.users()
+			.set_pb_notifications(user_id, false, None)
+			.await?;
+
+		ctx.say("Successfully disabled PB notifications.")
+			.await?;
+
+		return Ok(());
+	}
+
+	let steam_id = user.steam_id.ok_or(Error::NoSteamID)?;
+
+	if !user.verified {
+		return Err(Error::NotVerified);
+	}
+
+	let channel_id = match destination_choice {
+		None | Some(NotificationChoice::DM) => None,
+		Some(NotificationChoice::Channel) => {
+			if ctx.guild_id().is_none() {
+				return Err(Error::Custom(String::from(
+					"You can only receive notifications in server channels.",
+				)));
+			}
+
+			ctx.fetch_guild()
+				.await
+				.and_then(|guild| guild.pb_feed_channel_id)
+				.or(Some(*ctx.channel_id().as_u64()))
+		}
+	};
+
+	pb_notifications::snapshot_pbs(user_id, steam_id, ctx.gokz_client(), ctx.db()).await?;
+
+	let last_record_id = schnose_api::get_recent(steam_id.into(), 1, ctx.gokz_client())
+		.await
+		.ok()
+		.and_then(|records| records.first().map(|record| record.id));
+
+	// Set this first, so the background task doesn't notify about records we already know.
+	ctx.users()
+		.set_last_record_id(user_id, last_record_id)
+		.await?;
+	ctx.users()
+		.set_pb_notifications(user_id, true, channel_id)
+		.await?;
+
+	ctx.say(match channel_id {
+		None => {
+			String::from("Successfully enabled PB notifications! You will receive them via DM.")
+		}
+		Some(channel_id) => format!(
+			"Successfully enabled PB notifications! You will receive them in <#{channel_id}>."
+		),
+	})
+	.await?;
+
+	Ok(())
+}
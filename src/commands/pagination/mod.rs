@@ -4,10 +4,42 @@ use {
 	std::time::Duration,
 };
 
+/// An extra button next to the arrows of [`paginate_with_buttons`].
+#[derive(Debug, Clone, Copy)]
+pub struct Button {
+	/// Tells the buttons apart when one of them is pressed
+	pub name: &'static str,
+	pub label: char,
+}
+
+/// What happens after a [`Button`] has been pressed.
+#[derive(Debug, Clone)]
+pub enum Action {
+	/// Replace the current page.
+	Update(CreateEmbed),
+
+	/// Post a new message, so the pages stay where they are.
+	Post(CreateEmbed),
+}
+
 pub async fn paginate(ctx: &Context<'_>, embeds: Vec<CreateEmbed>) -> Result<()> {
+	paginate_with_buttons(ctx, embeds, &[], |_, _| unreachable!("There are no extra buttons."))
+		.await
+}
+
+/// Like [`paginate`], but with extra `buttons` after the arrows. When one of them is pressed,
+/// `on_click` gets called with the button's name and the index of the current page. The arrows
+/// are left out if there is only one page.
+pub async fn paginate_with_buttons(
+	ctx: &Context<'_>,
+	mut embeds: Vec<CreateEmbed>,
+	buttons: &[Button],
+	mut on_click: impl FnMut(&str, usize) -> Action,
+) -> Result<()> {
 	let ctx_id = ctx.id();
 	let prev_id = format!("{ctx_id}_prev");
 	let next_id = format!("{ctx_id}_next");
+	let button_id = |button: &Button| format!("{ctx_id}_{}", button.name);
 
 	// Send first embed
 	ctx.send(|reply| {
@@ -18,8 +50,20 @@ pub async fn paginate(ctx: &Context<'_>, embeds: Vec<CreateEmbed>) -> Result<()>
 			})
 			.components(|componends| {
 				componends.create_action_row(|row| {
-					row.create_button(|button| button.custom_id(&prev_id).label('◀'))
-						.create_button(|button| button.custom_id(&next_id).label('▶'))
+					if embeds.len() > 1 {
+						row.create_button(|button| button.custom_id(&prev_id).label('◀'))
+							.create_button(|button| button.custom_id(&next_id).label('▶'));
+					}
+
+					for extra in buttons {
+						row.create_button(|button| {
+							button
+								.custom_id(button_id(extra))
+								.label(extra.label)
+						});
+					}
+
+					row
 				})
 			})
 	})
@@ -40,6 +84,29 @@ pub async fn paginate(ctx: &Context<'_>, embeds: Vec<CreateEmbed>) -> Result<()>
 	{
 		let interaction_id = &interaction.data.custom_id;
 
+		if let Some(extra) = buttons
+			.iter()
+			.find(|button| button_id(button) == *interaction_id)
+		{
+			let (kind, embed) = match on_click(extra.name, current_page) {
+				Action::Update(embed) => {
+					embeds[current_page] = embed.clone();
+					(InteractionResponseType::UpdateMessage, embed)
+				}
+				Action::Post(embed) => (InteractionResponseType::ChannelMessageWithSource, embed),
+			};
+
+			interaction
+				.create_interaction_response(ctx, |response| {
+					response
+						.kind(kind)
+						.interaction_response_data(|data| data.set_embed(embed))
+				})
+				.await?;
+
+			continue;
+		}
+
 		// Not the interaction we're looking for
 		if interaction_id != &prev_id && interaction_id != &next_id {
 			continue;
@@ -1,13 +1,21 @@
 use {
-	super::custom_params::ModeChoice,
+	super::{
+		autocomplete,
+		custom_params::{BoolChoice, ModeChoice},
+	},
 	crate::{
 		error::{Error, Result},
+		render,
 		state::{Context, StateContainer},
+		stats::PlayerStats,
 		target::Target,
+		utils,
 	},
-	gokz_rs::{global_api, kzgo_api, schnose_api, Mode, Rank},
-	num_format::{Locale, ToFormattedString},
-	std::collections::{hash_map::RandomState, HashMap},
+	gokz_rs::{Mode, Rank},
+	num_format::ToFormattedString,
+	poise::serenity_prelude::AttachmentType,
+	std::borrow::Cow,
+	tracing::error,
 };
 
 /// Points, completion and WR stats for a particular player.
@@ -23,15 +31,19 @@ use {
 /// - `player`: this can be any string. The bot will try its best to interpret it as something \
 ///   useful. If you want to help it with that, specify one of the following:
 ///   - a `SteamID`, e.g. `STEAM_1:1:161178172`, `U:1:322356345` or `76561198282622073`
+///   - a link to a Steam or KZ:GO profile, e.g. `https://steamcommunity.com/id/alphakeks`
 ///   - a `Mention`, e.g. `@MyBestFriend`
-///   - a player's name, e.g. `AlphaKeks`
+///   - a player's name, e.g. `AlphaKeks`. If several players match, the bot will ask you \
+///     which one you mean.
 ///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
 ///     find one, or you don't have a SteamID set, the command will fail. To save a mode \
 ///     preference in the database, see `/setsteam`.
 /// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-///     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-///     preference in the database, see `/mode`.
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
+/// - `text`: by default, completion stats are shown as an image. If that doesn't work well for \
+///   you, set this to `Yes` to get the classic text version instead.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, on_error = "Error::handle")]
 pub async fn profile(
@@ -39,13 +51,18 @@ pub async fn profile(
 
 	#[description = "The player you want to look up."]
 	#[rename = "player"]
+	#[autocomplete = "autocomplete::player_name"]
 	target: Option<String>,
 
 	#[description = "KZT/SKZ/VNL"]
 	#[rename = "mode"]
 	mode_choice: Option<ModeChoice>,
+
+	#[description = "Show the stats as text instead of an image."]
+	#[rename = "text"]
+	text_only_choice: Option<BoolChoice>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	// let db_entry = ctx
 	// 	.find_user_by_id(*ctx.author().id.as_u64())
@@ -66,139 +83,81 @@ pub async fn profile(
 		None => ModeChoice::figure_out(target.clone(), &ctx).await,
 	};
 
-	let player_identifier = target.clone().into_player(&ctx).await;
-
-	let tp = global_api::get_player_records(
-		player_identifier.clone(),
-		mode,
-		true,
-		0,
-		9999,
-		ctx.gokz_client(),
-	)
-	.await
-	.unwrap_or_default();
-
-	let pro = global_api::get_player_records(
-		player_identifier.clone(),
-		mode,
-		false,
-		0,
-		9999,
-		ctx.gokz_client(),
-	)
-	.await
-	.unwrap_or_default();
+	let player_identifier = target.clone().into_player(&ctx).await?;
+
+	let tp = ctx
+		.api_cache()
+		.get_player_records(&player_identifier, mode, true, ctx.gokz_client())
+		.await
+		.or_else(|why| match why {
+			Error::ApiUnavailable { .. } => Err(why),
+			_ => Ok(Vec::new()),
+		})?;
+
+	let pro = ctx
+		.api_cache()
+		.get_player_records(&player_identifier, mode, false, ctx.gokz_client())
+		.await
+		.or_else(|why| match why {
+			Error::ApiUnavailable { .. } => Err(why),
+			_ => Ok(Vec::new()),
+		})?;
 
 	if tp.is_empty() && pro.is_empty() {
 		return Err(Error::NoRecords);
 	}
 
-	let player = schnose_api::get_player(player_identifier, ctx.gokz_client()).await?;
+	let player = ctx
+		.api_cache()
+		.get_player(&player_identifier, ctx.gokz_client())
+		.await?;
+
+	ctx.recent_players()
+		.remember(&player.name, player.steam_id);
 
-	let mut tp_points = 0;
-	let mut pro_points = 0;
-	let mut completion_count = [(0, 0); 8];
-	let mut tp_wrs = 0;
-	let mut pro_wrs = 0;
 	let (total_tp_records, total_pro_records) = match mode {
 		Mode::KZTimer => (player.records.kzt.tp, player.records.kzt.pro),
 		Mode::SimpleKZ => (player.records.skz.tp, player.records.skz.pro),
 		Mode::Vanilla => (player.records.vnl.tp, player.records.vnl.pro),
 	};
-	let mut tp_maps: HashMap<u16, u8, RandomState> = HashMap::from_iter(
-		ctx.maps()
-			.iter()
-			.map(|map| (map.id, map.tier as u8)),
-	);
-	let mut pro_maps = tp_maps.clone();
-
-	let len = tp.len().max(pro.len());
-	for i in 0..len {
-		if tp.len() > i {
-			let map_id = tp[i].map_id;
-			if let Some(tier) = tp_maps.remove(&map_id) {
-				let points = tp[i].points;
-
-				tp_points += points;
-				completion_count[0].0 += 1;
-				completion_count[tier as usize].0 += 1;
-
-				if points == 1000 {
-					tp_wrs += 1;
-				}
-			}
-		}
-
-		if pro.len() > i {
-			let map_id = pro[i].map_id;
-			if let Some(tier) = pro_maps.remove(&map_id) {
-				let points = pro[i].points;
-
-				pro_points += points;
-				completion_count[0].1 += 1;
-				completion_count[tier as usize].1 += 1;
-
-				if points == 1000 {
-					pro_wrs += 1;
-				}
-			}
-		}
-	}
-
-	let total_points = tp_points + pro_points;
-	let rank = Rank::from_points(total_points, mode);
-
-	let completion_stats = kzgo_api::get_completions(mode, ctx.gokz_client()).await?;
-	let mut completion_percentages = [(0f64, 0f64); 8];
-
-	for i in 0..8 {
-		// player has completed maps in this tier in tp
-		if completion_count[i].0 > 0 {
-			let count = completion_count[i].0;
-			let max_count = completion_stats.tp[i];
-			completion_percentages[i].0 = (count as f64 / max_count as f64) * 100f64;
-		}
-
-		// player has completed maps in this tier in pro
-		if completion_count[i].1 > 0 {
-			let count = completion_count[i].1;
-			let max_count = completion_stats.pro[i];
-			completion_percentages[i].1 = (count as f64 / max_count as f64) * 100f64;
-		}
-	}
-
-	let mut bars = [[""; 7]; 2].map(|bars| bars.map(String::from));
 
-	for (i, percentage) in completion_percentages
-		.iter()
-		.skip(1)
-		.enumerate()
-	{
-		let amount = (percentage.0 / 10f64) as u32;
+	let stats = PlayerStats::new(
+		tp.iter()
+			.map(|record| (record.map_id, record.points)),
+		pro.iter()
+			.map(|record| (record.map_id, record.points)),
+		&ctx.maps(),
+	);
 
-		for _ in 0..amount {
-			bars[0][i].push('█');
-		}
+	let rank = Rank::from_points(stats.total_points(), mode);
 
-		for _ in 0..(10 - amount) {
-			bars[0][i].push('░');
-		}
+	let completion_stats = ctx
+		.api_cache()
+		.get_completions(mode, ctx.gokz_client())
+		.await?;
 
-		let amount = (percentage.1 / 10f64) as u32;
+	let max_completions: [(u32, u32); 8] =
+		std::array::from_fn(|i| (completion_stats.tp[i] as u32, completion_stats.pro[i] as u32));
 
-		for _ in 0..amount {
-			bars[1][i].push('█');
-		}
+	let completion_count = stats.completions;
+	let completion_percentages = stats.completion_percentages(max_completions);
 
-		for _ in 0..(10 - amount) {
-			bars[1][i].push('░');
-		}
-	}
+	let tiers = completion_percentages.iter().skip(1);
+	let bars = [
+		tiers
+			.clone()
+			.map(|(tp, _)| utils::progress_bar(*tp, 10))
+			.collect::<Vec<_>>(),
+		tiers
+			.map(|(_, pro)| utils::progress_bar(*pro, 10))
+			.collect::<Vec<_>>(),
+	];
 
 	let fav_mode = ctx
 		.fetch_user(target)
-		.await
+		.await?
+		// Anyone can claim any SteamID with `/setsteam`, so we only trust verified links here.
+		.filter(|user| user.verified)
 		.map_or_else(
 			|| String::from("unknown"),
 			|user| {
@@ -207,6 +166,8 @@ pub async fn profile(
 			},
 		);
 
+	let locale = ctx.locale().await;
+
 	let description = format!(
 		r#"
 🏆 **TP**: {}
@@ -229,8 +190,8 @@ Total PRO runs: {}
 Points: **{} ({})**
 Preferred Mode: {}
 		"#,
-		tp_wrs,
-		pro_wrs,
+		stats.tp_wrs,
+		stats.pro_wrs,
 		completion_count[0].0,
 		completion_stats.tp[0],
 		completion_percentages[0].0,
@@ -253,17 +214,64 @@ Preferred Mode: {}
 		bars[1][6],
 		total_tp_records,
 		total_pro_records,
-		total_points.to_formatted_string(&Locale::en),
+		stats
+			.total_points()
+			.to_formatted_string(&locale),
 		rank,
 		fav_mode
 	);
 
-	let avatar = kzgo_api::get_avatar(player.steam_id, ctx.gokz_client())
+	let avatar = ctx
+		.fetch_avatar(player.steam_id)
 		.await
-		.map(|user| user.avatar_url)
 		.unwrap_or_default();
 
+	let card = match text_only_choice {
+		Some(BoolChoice::Yes) => None,
+		_ => {
+			let card = render::ProfileCard {
+				player_name: player.name.clone(),
+				mode: mode.short().to_string(),
+				rank: rank.to_string(),
+				points: stats
+					.total_points()
+					.to_formatted_string(&locale),
+				tp_wrs: stats.tp_wrs,
+				pro_wrs: stats.pro_wrs,
+				completions: stats.completions,
+				max_completions,
+				avatar: render::fetch_avatar(&avatar, ctx.gokz_client()).await,
+				color: ctx.color(),
+			};
+
+			match tokio::task::spawn_blocking(move || card.render())
+				.await
+				.unwrap_or_else(|why| Err(Error::Custom(why.to_string())))
+			{
+				Ok(png) => Some(png),
+				Err(why) => {
+					error!("Failed to render profile card: {why:?}");
+					None
+				}
+			}
+		}
+	};
+
+	let summary = format!(
+		"🏆 **TP**: {} | **PRO**: {}\nTotal runs: {} TP | {} PRO\nPreferred Mode: {}",
+		stats.tp_wrs, stats.pro_wrs, total_tp_records, total_pro_records, fav_mode
+	);
+
+	let has_card = card.is_some();
+
 	ctx.send(|reply| {
+		if let Some(png) = card {
+			reply.attachment(AttachmentType::Bytes {
+				data: Cow::Owned(png),
+				filename: String::from("profile.png"),
+			});
+		}
+
 		reply.embed(|embed| {
 			embed
 				.color(ctx.color())
@@ -273,13 +281,21 @@ Preferred Mode: {}
 					&player.steam_id,
 					mode.short().to_lowercase()
 				))
-				.thumbnail(avatar)
-				.description(description)
 				.footer(|footer| {
 					footer
 						.text(format!("{} | SteamID: {}", ctx.schnose(), &player.steam_id))
 						.icon_url(ctx.icon_url())
-				})
+				});
+
+			if has_card {
+				embed
+					.description(summary)
+					.image("attachment://profile.png")
+			} else {
+				embed
+					.thumbnail(avatar)
+					.description(description)
+			}
 		})
 	})
 	.await?;
@@ -0,0 +1,284 @@
+use {
+	super::custom_params::{BoolChoice, ModeChoice},
+	crate::{
+		error::{Error, Result},
+		progress::{self, Snapshot},
+		render,
+		state::{Context, StateContainer},
+		target::Target,
+		utils,
+	},
+	chrono::Utc,
+	num_format::{Locale, ToFormattedString},
+	poise::serenity_prelude::AttachmentType,
+	std::borrow::Cow,
+	tracing::error,
+};
+
+/// How a player's stats changed over time.
+///
+/// The bot regularly saves the points, WRs and completions of every player who linked their \
+/// SteamID with `/setsteam`. This command will show you how these stats changed over a certain \
+/// period of time. You may specify the following parameters:
+///
+/// - `player`: this can be any string. The bot will try its best to interpret it as something \
+///   useful. If you want to help it with that, specify one of the following:
+///   - a `SteamID`, e.g. `STEAM_1:1:161178172`, `U:1:322356345` or `76561198282622073`
+///   - a link to a Steam or KZ:GO profile, e.g. `https://steamcommunity.com/id/alphakeks`
+///   - a `Mention`, e.g. `@MyBestFriend`
+///   - a player's name, e.g. `AlphaKeks`
+///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
+///     find one, or you don't have a SteamID set, the command will fail. To save a SteamID in \
+///     the database, see `/setsteam`.
+/// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
+/// - `days`: how far back to look. If you don't specify this, the bot will default to `30`.
+/// - `text`: by default, the points chart is shown as an image. If that doesn't work well for \
+///   you, set this to `Yes` to get a text version instead.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, on_error = "Error::handle")]
+pub async fn progress(
+	ctx: Context<'_>,
+
+	#[description = "The player you want to look up."]
+	#[rename = "player"]
+	target: Option<String>,
+
+	#[description = "KZT/SKZ/VNL"]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "How many days to look back"]
+	#[min = 1]
+	#[max = 365]
+	days: Option<u16>,
+
+	#[description = "Show the points chart as text instead of an image."]
+	#[rename = "text"]
+	text_only_choice: Option<BoolChoice>,
+) -> Result<()> {
+	ctx.defer_reply().await?;
+
+	let target: Target = match target {
+		None => ctx.author_id().into(),
+		Some(target) => target.parse()?,
+	};
+
+	let mode = match mode_choice {
+		Some(choice) => choice.into(),
+		None => ModeChoice::figure_out(target.clone(), &ctx).await,
+	};
+
+	let days = days.unwrap_or(30);
+
+	// Snapshots are only taken for linked users, so there is no point in asking the APIs.
+	let steam_id = match target.clone() {
+		Target::SteamID { steam_id } => steam_id,
+		target => ctx
+			.fetch_user(target.clone())
+			.await?
+			.ok_or(Error::UserNotInDatabase { user: target })?
+			.steam_id
+			.ok_or(Error::NoSteamID)?,
+	};
+
+	let since = Utc::now() - chrono::Duration::days(days.into());
+	let snapshots = progress::fetch_snapshots(steam_id, mode, since, ctx.db()).await?;
+
+	let (first, last) = match (snapshots.first(), snapshots.last()) {
+		(Some(first), Some(last)) if snapshots.len() > 1 => (first, last),
+		_ => return Err(Error::NotEnoughProgressData),
+	};
+
+	let completion_stats = ctx
+		.api_cache()
+		.get_completions(mode, ctx.gokz_client())
+		.await?;
+
+	let max_completions: [(u32, u32); 8] =
+		std::array::from_fn(|i| (completion_stats.tp[i] as u32, completion_stats.pro[i] as u32));
+
+	let percentages = last
+		.stats
+		.completion_percentages(max_completions);
+
+	let tiers = (1..8)
+		.map(|tier| {
+			let (tp, pro) = last.stats.completions[tier];
+			let (old_tp, old_pro) = first.stats.completions[tier];
+
+			format!(
+				"T{tier} ⌠ {} ⌡ {:<4} ⌠ {} ⌡ {}",
+				utils::progress_bar(percentages[tier].0, 10),
+				delta(old_tp, tp),
+				utils::progress_bar(percentages[tier].1, 10),
+				delta(old_pro, pro),
+			)
+		})
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	let chart = match text_only_choice {
+		Some(BoolChoice::Yes) => None,
+		_ => {
+			let points = snapshots
+				.iter()
+				.map(|snapshot| (snapshot.created_at, snapshot.stats.total_points()))
+				.collect::<Vec<_>>();
+
+			let color = ctx.color();
+
+			match tokio::task::spawn_blocking(move || render::points_chart(&points, color))
+				.await
+				.unwrap_or_else(|why| Err(Error::Custom(why.to_string())))
+			{
+				Ok(png) => Some(png),
+				Err(why) => {
+					error!("Failed to render points chart: {why:?}");
+					None
+				}
+			}
+		}
+	};
+
+	let locale = ctx.locale().await;
+
+	let description = format!(
+		r#"
+Points: **{}** ({})
+🏆 **TP**: {} ({}) | **PRO**: {} ({})
+──────────────────────────────────────────
+```
+        [TP]                   [PRO]
+All {:>4} / {:<4} {:<4} {:>4} / {:<4} {}
+{}
+```
+{}
+		"#,
+		last.stats
+			.total_points()
+			.to_formatted_string(&locale),
+		delta(first.stats.total_points(), last.stats.total_points()),
+		last.stats.tp_wrs,
+		delta(first.stats.tp_wrs, last.stats.tp_wrs),
+		last.stats.pro_wrs,
+		delta(first.stats.pro_wrs, last.stats.pro_wrs),
+		last.stats.completions[0].0,
+		max_completions[0].0,
+		delta(first.stats.completions[0].0, last.stats.completions[0].0),
+		last.stats.completions[0].1,
+		max_completions[0].1,
+		delta(first.stats.completions[0].1, last.stats.completions[0].1),
+		tiers,
+		chart.as_ref().map_or_else(
+			|| format!("**Points over time**\n```\n{}\n```", points_chart(&snapshots, &locale)),
+			|_| String::from("**Points over time**"),
+		),
+	);
+
+	let player = match ctx
+		.api_cache()
+		.get_player(&steam_id.into(), ctx.gokz_client())
+		.await
+	{
+		Ok(player) => player.name,
+		Err(_) => ctx
+			.fetch_steam_name(steam_id)
+			.await
+			.unwrap_or_else(|| steam_id.to_string()),
+	};
+
+	let has_chart = chart.is_some();
+
+	ctx.send(|reply| {
+		if let Some(png) = chart {
+			reply.attachment(AttachmentType::Bytes {
+				data: Cow::Owned(png),
+				filename: String::from("progress.png"),
+			});
+		}
+
+		reply.embed(|embed| {
+			if has_chart {
+				embed.image("attachment://progress.png");
+			}
+
+			embed
+				.color(ctx.color())
+				.title(format!("[{}] {player} - last {days} days", mode.short()))
+				.url(format!("https://kzgo.eu/players/{steam_id}?{}=", mode.short().to_lowercase()))
+				.description(description)
+				.footer(|footer| {
+					footer
+						.text(format!(
+							"{} | Since: {}",
+							ctx.schnose(),
+							first.created_at.format("%Y-%m-%d")
+						))
+						.icon_url(ctx.icon_url())
+				})
+		})
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// `+3`, `-1` or `±0`
+fn delta(old: u32, new: u32) -> String {
+	match new as i64 - old as i64 {
+		0 => String::from("±0"),
+		delta => format!("{delta:+}"),
+	}
+}
+
+/// One bar per snapshot, scaled between the lowest and highest points in the period. If there are
+/// too many snapshots, only some of them are shown.
+fn points_chart(snapshots: &[Snapshot], locale: &Locale) -> String {
+	const ROWS: usize = 10;
+
+	let step = (snapshots.len() as f64 / ROWS as f64)
+		.ceil()
+		.max(1.0) as usize;
+
+	let mut rows = snapshots
+		.iter()
+		.step_by(step)
+		.collect::<Vec<_>>();
+
+	// Always show the most recent snapshot.
+	if let Some(last) = snapshots.last() {
+		if rows
+			.last()
+			.map_or(true, |row| row.created_at != last.created_at)
+		{
+			rows.push(last);
+		}
+	}
+
+	let points = rows
+		.iter()
+		.map(|snapshot| snapshot.stats.total_points());
+	let min = points.clone().min().unwrap_or_default();
+	let max = points.max().unwrap_or_default();
+
+	rows.into_iter()
+		.map(|snapshot| {
+			let points = snapshot.stats.total_points();
+			let percentage = match max - min {
+				0 => 100f64,
+				range => ((points - min) as f64 / range as f64) * 100f64,
+			};
+
+			format!(
+				"{} {} {}",
+				snapshot.created_at.format("%m-%d"),
+				utils::progress_bar(percentage, 20),
+				points.to_formatted_string(locale)
+			)
+		})
+		.collect::<Vec<_>>()
+		.join("\n")
+}
@@ -1,16 +1,49 @@
 use {
-	super::custom_params::TierChoice,
+	super::{
+		autocomplete,
+		custom_params::{ModeChoice, RuntypeChoice, TierChoice},
+		map,
+		pagination::{self, Action, Button},
+	},
 	crate::{
 		error::{Error, Result},
 		state::{Context, StateContainer},
+		target::Target,
+		utils,
 	},
+	gokz_rs::Tier,
+	poise::serenity_prelude::CreateEmbed,
 	rand::Rng,
+	schnosebot::global_map::GlobalMap,
+	std::collections::HashSet,
 };
 
 /// Get a random map name from the global map pool.
 ///
-/// This command will simply select a random map from the global map pool. You may specify a \
-/// `tier` if you want to.
+/// This command will simply select a random map from the global map pool. If you don't like the \
+/// map, you can re-roll it with the 🎲 button. You may specify the following options:
+///
+/// - `tier`: only pick maps of this tier
+/// - `player`: only pick maps this player hasn't finished yet. This can be any string. The bot \
+///   will try its best to interpret it as something useful. If you want to help it with that, \
+///   specify one of the following:
+///   - a `SteamID`, e.g. `STEAM_1:1:161178172`, `U:1:322356345` or `76561198282622073`
+///   - a link to a Steam or KZ:GO profile, e.g. `https://steamcommunity.com/id/alphakeks`
+///   - a `Mention`, e.g. `@MyBestFriend`
+///   - a player's name, e.g. `AlphaKeks`. If several players match, the bot will ask you \
+///     which one you mean.
+///   - If you don't specify this, but specify a `mode` or `runtype`, the bot will pick a map \
+///     _you_ haven't finished yet. For that it will search the database for your UserID. If it \
+///     can't find one, or you don't have a SteamID set, the command will fail. To save a SteamID \
+///     in the database, see `/setsteam`.
+/// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
+/// - `runtype`: `TP` / `PRO`
+///   - If you don't specify this, the bot will default to `PRO`.
+///
+/// If you specify none of `player`, `mode` and `runtype`, the bot will pick from every map.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn random(
@@ -19,60 +52,85 @@ pub async fn random(
 	#[description = "Filter by map difficulty"]
 	#[rename = "tier"]
 	tier_choice: Option<TierChoice>,
+
+	#[description = "Only pick maps this player hasn't finished yet."]
+	#[rename = "player"]
+	#[autocomplete = "autocomplete::player_name"]
+	target: Option<String>,
+
+	#[description = "KZT/SKZ/VNL"]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "TP/PRO"]
+	#[rename = "runtype"]
+	runtype_choice: Option<RuntypeChoice>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
-	let mut filtered_maps = ctx
-		.maps()
+	let maps = ctx.maps();
+	let mut filtered_maps = maps
 		.iter()
 		.filter(|map| tier_choice.map_or(true, |tier| map.tier as u8 == tier as u8))
+		.cloned()
 		.collect::<Vec<_>>();
 
-	let rng = rand::thread_rng().gen_range(0..filtered_maps.len());
+	let mut footer = None;
+
+	if target.is_some() || mode_choice.is_some() || runtype_choice.is_some() {
+		let target: Target = match target {
+			None => ctx.author_id().into(),
+			Some(target) => target.parse()?,
+		};
+
+		let mode = match mode_choice {
+			Some(choice) => choice.into(),
+			None => ModeChoice::figure_out(ctx.author_id().into(), &ctx).await,
+		};
+
+		let runtype = matches!(runtype_choice, Some(RuntypeChoice::TP));
 
-	let map = filtered_maps.remove(rng);
+		let player = target.into_player(&ctx).await?;
 
-	let mapper = match &map.mapper_steam_id {
-		None => map.mapper_name.clone(),
-		Some(steam_id) => format!(
-			"[{}](https://steamcommunity.com/profiles/{})",
-			map.mapper_name,
-			steam_id.as_id64()
-		),
+		let tier = tier_choice.map(Tier::from);
+		let unfinished = ctx
+			.api_cache()
+			.get_unfinished(&player, mode, runtype, tier, ctx.gokz_client())
+			.await?
+			.ok_or(Error::Custom(String::from("Congrats! There are no maps left to finish 🥳")))?
+			.into_iter()
+			.map(|map| map.name)
+			.collect::<HashSet<_>>();
+
+		filtered_maps.retain(|map| utils::has_filter(map, mode) && unfinished.contains(&map.name));
+
+		footer = Some(format!(
+			"{} {} | {} unfinished maps",
+			mode.short(),
+			if runtype { "TP" } else { "PRO" },
+			filtered_maps.len()
+		));
+	}
+
+	if filtered_maps.is_empty() {
+		return Err(Error::Custom(String::from("No maps match these filters.")));
+	}
+
+	let pick = |maps: &[GlobalMap]| -> CreateEmbed {
+		let rng = rand::thread_rng().gen_range(0..maps.len());
+		let mut embed = map::embed(&maps[rng], ctx.color());
+
+		if let Some(footer) = &footer {
+			embed.footer(|f| f.text(footer).icon_url(ctx.icon_url()));
+		}
+
+		embed
 	};
 
-	let kzt_filer = if map.kzt { "✅" } else { "❌" };
-	let skz_filer = if map.skz { "✅" } else { "❌" };
-	let vnl_filer = if map.vnl { "✅" } else { "❌" };
-
-	ctx.send(|reply| {
-		reply.embed(|embed| {
-			embed
-				.color(ctx.color())
-				.title(&map.name)
-				.url(&map.kzgo_link())
-				.thumbnail(&map.thumbnail())
-				.description(format!(
-					r#"
-🡆 Tier: {} ({})
-🡆 Mapper(s): {}
-🡆 Bonuses: {}
-🡆 Last Updated: {}
-
-🡆 Filters:
-					"#,
-					map.tier as u8,
-					map.tier,
-					mapper,
-					map.courses.len() - 1,
-					map.updated_on.format("%d/%m/%Y"),
-				))
-				.field("KZT", kzt_filer, true)
-				.field("SKZ", skz_filer, true)
-				.field("VNL", vnl_filer, true)
-		})
-	})
-	.await?;
+	let reroll = Button { name: "reroll", label: '🎲' };
 
-	Ok(())
+	pagination::paginate_with_buttons(&ctx, vec![pick(&filtered_maps)], &[reroll], |_, _| {
+		Action::Update(pick(&filtered_maps))
+	})
+	.await
 }
@@ -1,11 +1,10 @@
 use {
-	super::pagination,
+	super::{autocomplete, pagination},
 	crate::{
 		error::{Error, Result},
 		state::{Context, StateContainer},
 		target::Target,
 	},
-	gokz_rs::{global_api, schnose_api},
 	poise::serenity_prelude::CreateEmbed,
 	schnosebot::time,
 };
@@ -19,8 +18,10 @@ use {
 /// will try its best to interpret it as something useful. If you want to help it with that, \
 /// specify one of the following:
 ///   - a `SteamID`, e.g. `STEAM_1:1:161178172`, `U:1:322356345` or `76561198282622073`
+///   - a link to a Steam or KZ:GO profile, e.g. `https://steamcommunity.com/id/alphakeks`
 ///   - a `Mention`, e.g. `@MyBestFriend`
-///   - a player's name, e.g. `AlphaKeks`
+///   - a player's name, e.g. `AlphaKeks`. If several players match, the bot will ask you \
+///     which one you mean.
 ///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
 ///     find one, or you don't have a SteamID set, the command will fail. To save a mode \
 ///     preference in the database, see `/setsteam`.
@@ -31,18 +32,27 @@ pub async fn recent(
 
 	#[description = "The player you want to target."]
 	#[rename = "player"]
+	#[autocomplete = "autocomplete::player_name"]
 	target: Option<String>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let target: Target = match target {
 		None => ctx.author_id().into(),
 		Some(target) => target.parse()?,
 	};
 
-	let player = target.into_player(&ctx).await;
+	let player = target.into_player(&ctx).await?;
 
-	let recent_records = schnose_api::get_recent(player, 10, ctx.gokz_client()).await?;
+	let recent_records = ctx
+		.api_cache()
+		.get_recent(&player, 10, ctx.gokz_client())
+		.await?;
+
+	if let Some(record) = recent_records.first() {
+		ctx.recent_players()
+			.remember(&record.player.name, record.player.steam_id);
+	}
 
 	let mut embeds = Vec::new();
 
@@ -50,7 +60,9 @@ pub async fn recent(
 	let max_pages = recent_records.len();
 
 	for (page_idx, record) in recent_records.into_iter().enumerate() {
-		let place = global_api::get_place(record.id, ctx.gokz_client())
+		let place = ctx
+			.api_cache()
+			.get_place(record.id, ctx.gokz_client())
 			.await
 			.map(|place| format!("[#{place}]"))
 			.unwrap_or_default();
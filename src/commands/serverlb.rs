@@ -0,0 +1,217 @@
+use {
+	super::{
+		custom_params::{LeaderboardMetric, ModeChoice, RuntypeChoice},
+		pagination,
+	},
+	crate::{
+		api_cache::MAX_CONCURRENT_REQUESTS,
+		database::User,
+		error::{Error, Result},
+		state::{Context, StateContainer},
+		stats::PlayerStats,
+	},
+	futures::StreamExt,
+	gokz_rs::{Mode, PlayerIdentifier},
+	num_format::ToFormattedString,
+	poise::serenity_prelude::CreateEmbed,
+};
+
+/// Leaderboard of this server's members.
+///
+/// This command will rank every member of this server who linked their SteamID with `/setsteam` \
+/// and verified it with `/verify`. You may specify the following options:
+///
+/// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
+/// - `runtype`: `TP` / `PRO`
+///   - If you don't specify this, the bot will count both.
+/// - `metric`: what to rank players by
+///   - `Points`: total points (default)
+///   - `Completions`: how many maps a player has finished
+///   - `World Records`: how many world records a player holds
+///   - `Average Points`: average points per finished map
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, guild_only, ephemeral, on_error = "Error::handle")]
+pub async fn serverlb(
+	ctx: Context<'_>,
+
+	#[description = "KZT/SKZ/VNL"]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "TP/PRO"]
+	#[rename = "runtype"]
+	runtype_choice: Option<RuntypeChoice>,
+
+	#[description = "What to rank players by"]
+	#[rename = "metric"]
+	metric_choice: Option<LeaderboardMetric>,
+) -> Result<()> {
+	ctx.defer_reply().await?;
+
+	let mode = match mode_choice {
+		Some(choice) => choice.into(),
+		None => ModeChoice::figure_out(ctx.author_id().into(), &ctx).await,
+	};
+
+	let runtype = runtype_choice.map(bool::from);
+	let metric = metric_choice.unwrap_or(LeaderboardMetric::Points);
+
+	let members = ctx.fetch_verified_members().await?;
+
+	let mut leaderboard = Vec::new();
+
+	let stats = futures::stream::iter(&members)
+		.map(|member| member_stats(&ctx, member, mode, runtype))
+		.buffer_unordered(MAX_CONCURRENT_REQUESTS)
+		.collect::<Vec<_>>()
+		.await;
+
+	for result in stats {
+		if let Some(entry) = result? {
+			leaderboard.push(entry);
+		}
+	}
+
+	if leaderboard.is_empty() {
+		return Err(Error::NoRecords);
+	}
+
+	leaderboard
+		.sort_by(|(_, a), (_, b)| metric_value(metric, b).total_cmp(&metric_value(metric, a)));
+
+	let locale = ctx.locale().await;
+
+	let runtype = match runtype {
+		None => "TP & PRO",
+		Some(true) => "TP",
+		Some(false) => "PRO",
+	};
+
+	let mut embeds = Vec::new();
+	let mut temp = CreateEmbed::default();
+
+	// How many entries per page
+	let chunk_size = 12;
+	let max_pages = (leaderboard.len() as f64 / chunk_size as f64).ceil() as u8;
+	let mut place = 1;
+
+	for (page_idx, players) in leaderboard
+		.chunks(chunk_size)
+		.enumerate()
+	{
+		temp.color(ctx.color())
+			.title(format!("[{} {runtype}] Server Leaderboard - {metric}", mode.short()))
+			.footer(|footer| {
+				footer.text(format!("{} | Page {} / {}", ctx.schnose(), page_idx + 1, max_pages))
+			});
+
+		for (player_name, stats) in players {
+			let value = match metric {
+				LeaderboardMetric::Points => stats
+					.total_points()
+					.to_formatted_string(&locale),
+				LeaderboardMetric::Completions => format!("{} maps", completions(stats)),
+				LeaderboardMetric::WRs => format!("{} WRs", stats.tp_wrs + stats.pro_wrs),
+				LeaderboardMetric::AveragePoints => {
+					format!(
+						"{:.1} avg. points",
+						metric_value(LeaderboardMetric::AveragePoints, stats)
+					)
+				}
+			};
+
+			temp.field(format!("{player_name} [#{place}]"), value, true);
+			place += 1;
+		}
+
+		embeds.push(temp);
+		temp = CreateEmbed::default();
+	}
+
+	match embeds.len() {
+		0 => unreachable!(),
+		1 => {
+			ctx.send(|reply| {
+				reply.embed(|embed| {
+					*embed = embeds.remove(0);
+					embed
+				})
+			})
+			.await?;
+		}
+		_ => pagination::paginate(&ctx, embeds).await?,
+	}
+
+	Ok(())
+}
+
+/// Name and stats of a single member. `runtype` is `None` if both TP and PRO should be counted.
+async fn member_stats(
+	ctx: &Context<'_>,
+	member: &User,
+	mode: Mode,
+	runtype: Option<bool>,
+) -> Result<Option<(String, PlayerStats)>> {
+	let Some(steam_id) = member.steam_id else {
+		return Ok(None);
+	};
+
+	let player: PlayerIdentifier = steam_id.into();
+	let mut player_name = member.name.clone();
+	let (mut tp, mut pro) = (Vec::new(), Vec::new());
+
+	for current_runtype in [true, false] {
+		if runtype.map_or(false, |runtype| runtype != current_runtype) {
+			continue;
+		}
+
+		let records = ctx
+			.api_cache()
+			.get_player_records(&player, mode, current_runtype, ctx.gokz_client())
+			.await
+			.or_else(|why| match why {
+				Error::ApiUnavailable { .. } => Err(why),
+				_ => Ok(Vec::new()),
+			})?;
+
+		if let Some(record) = records.first() {
+			player_name = record.player_name.clone();
+		}
+
+		let records = records
+			.iter()
+			.map(|record| (record.map_id, record.points))
+			.collect::<Vec<_>>();
+
+		if current_runtype {
+			tp = records;
+		} else {
+			pro = records;
+		}
+	}
+
+	if tp.is_empty() && pro.is_empty() {
+		return Ok(None);
+	}
+
+	Ok(Some((player_name, PlayerStats::new(tp, pro, &ctx.maps()))))
+}
+
+fn completions(stats: &PlayerStats) -> u32 {
+	stats.completions[0].0 + stats.completions[0].1
+}
+
+fn metric_value(metric: LeaderboardMetric, stats: &PlayerStats) -> f64 {
+	match metric {
+		LeaderboardMetric::Points => stats.total_points() as f64,
+		LeaderboardMetric::Completions => completions(stats) as f64,
+		LeaderboardMetric::WRs => (stats.tp_wrs + stats.pro_wrs) as f64,
+		LeaderboardMetric::AveragePoints => match completions(stats) {
+			0 => 0f64,
+			completions => stats.total_points() as f64 / completions as f64,
+		},
+	}
+}
@@ -0,0 +1,171 @@
+use {
+	super::{
+		autocomplete,
+		custom_params::{ModeChoice, RuntypeChoice},
+		pagination,
+	},
+	crate::{
+		api_cache::MAX_CONCURRENT_REQUESTS,
+		error::{Error, Result},
+		state::{Context, StateContainer},
+	},
+	futures::StreamExt,
+	gokz_rs::PlayerIdentifier,
+	poise::serenity_prelude::CreateEmbed,
+	schnosebot::time,
+};
+
+/// Records of this server's members on a map.
+///
+/// This command will fetch the personal bests of every member of this server who verified their \
+/// SteamID with `/verify` on a particular map, and rank them by time. Every entry also shows the \
+/// global placement of that record. You are required to specify a `map` and may also specify \
+/// the following options:
+///
+/// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
+/// - `runtype`: `TP` / `PRO`
+///   - If you don't specify this, the bot will default to `PRO`.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, guild_only, ephemeral, on_error = "Error::handle")]
+pub async fn servertop(
+	ctx: Context<'_>,
+
+	#[description = "Choose a map"]
+	#[rename = "map"]
+	#[autocomplete = "autocomplete::map_name"]
+	map_choice: String,
+
+	#[description = "KZT/SKZ/VNL"]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "TP/PRO"]
+	#[rename = "runtype"]
+	runtype_choice: Option<RuntypeChoice>,
+) -> Result<()> {
+	ctx.defer_reply().await?;
+
+	let map = ctx.get_map(map_choice.clone())?;
+	ctx.recent_maps()
+		.remember(ctx.author_id(), &map.name);
+
+	let mode = match mode_choice {
+		Some(choice) => choice.into(),
+		None => ModeChoice::figure_out(ctx.author_id().into(), &ctx).await,
+	};
+
+	let runtype = matches!(runtype_choice, Some(RuntypeChoice::TP));
+
+	let members = ctx.fetch_verified_members().await?;
+
+	let steam_ids = members
+		.iter()
+		.filter_map(|member| member.steam_id);
+
+	let pbs = futures::stream::iter(steam_ids)
+		.map(|steam_id| {
+			let player: PlayerIdentifier = steam_id.into();
+			let map_name = map.name.clone();
+
+			async move {
+				ctx.api_cache()
+					.get_pb(&player, &map_name, mode, runtype, 0, ctx.gokz_client())
+					.await
+			}
+		})
+		.buffer_unordered(MAX_CONCURRENT_REQUESTS)
+		.collect::<Vec<_>>()
+		.await;
+
+	let mut records = Vec::new();
+
+	for pb in pbs {
+		match pb {
+			Ok(pb) => records.push(pb),
+			Err(why @ Error::ApiUnavailable { .. }) => return Err(why),
+			// The member simply doesn't have a record on this map.
+			Err(_) => {}
+		}
+	}
+
+	if records.is_empty() {
+		return Err(Error::NoRecords);
+	}
+
+	records.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+	let places = futures::stream::iter(&records)
+		.map(|record| {
+			ctx.api_cache()
+				.get_place(record.id, ctx.gokz_client())
+		})
+		.buffered(MAX_CONCURRENT_REQUESTS)
+		.collect::<Vec<_>>()
+		.await;
+
+	let mut embeds = Vec::new();
+	let mut temp = CreateEmbed::default();
+
+	// How many entries per page
+	let chunk_size = 12;
+	let max_pages = (records.len() as f64 / chunk_size as f64).ceil() as u8;
+	let mut place = 1;
+
+	for (page_idx, (records, places)) in records
+		.chunks(chunk_size)
+		.zip(places.chunks(chunk_size))
+		.enumerate()
+	{
+		temp.color(ctx.color())
+			.title(format!(
+				"[{} {}] Server records on {}",
+				mode.short(),
+				if runtype { "TP" } else { "PRO" },
+				&map.name
+			))
+			.url(format!("{}?{}=", map.kzgo_link(), mode.short().to_lowercase()))
+			.thumbnail(map.thumbnail())
+			.footer(|footer| {
+				footer.text(format!("{} | Page {} / {}", ctx.schnose(), page_idx + 1, max_pages))
+			});
+
+		for (record, global_place) in records.iter().zip(places) {
+			let title = format!("{} [#{}]", record.player_name, place);
+			let time = time::format(record.time);
+			let teleports = match record.teleports {
+				0 => String::new(),
+				1 => String::from("(1 TP)"),
+				n => format!("({n} TPs)"),
+			};
+			let global_place = global_place
+				.as_ref()
+				.map(|place| format!("(Global #{place})"))
+				.unwrap_or_default();
+
+			temp.field(title, format!("{time} {teleports} {global_place}"), true);
+			place += 1;
+		}
+
+		embeds.push(temp);
+		temp = CreateEmbed::default();
+	}
+
+	match embeds.len() {
+		0 => unreachable!(),
+		1 => {
+			ctx.send(|reply| {
+				reply.embed(|embed| {
+					*embed = embeds.remove(0);
+					embed
+				})
+			})
+			.await?;
+		}
+		_ => pagination::paginate(&ctx, embeds).await?,
+	}
+
+	Ok(())
+}
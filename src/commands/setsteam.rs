@@ -1,17 +1,20 @@
 use {
+	super::mode::Update,
 	crate::{
+		database::UserRepository,
 		error::{Error, Result},
 		state::{Context, StateContainer},
 	},
 	gokz_rs::SteamID,
-	sqlx::QueryBuilder,
 };
 
 /// Save your SteamID in the bot's database.
 ///
 /// This command will save your `SteamID` in its database for later use. Since many commands have \
 /// a `player` parameter you probably don't want to specify that over and over again. Instead you \
-/// can use this command and the bot will remember your choice in the future.
+/// can use this command and the bot will remember your choice in the future. Some features, \
+/// like server leaderboards and PB notifications, also require you to prove that the account \
+/// belongs to you; see `/verify`.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn setsteam(
@@ -26,64 +29,126 @@ pub async fn setsteam(
 
 	let (name, id) = (&ctx.author().name, ctx.author_id());
 
-	let table_name = &ctx.config().users_table;
+	let message = match set_steam_id(ctx.users(), name, id, steam_id).await? {
+		// The user already has a SteamID set and tried to set it to the same value.
+		// :tf:
+		Update::Unchanged => String::from("<:tf:999383331647012935>"),
+		Update::Updated => {
+			format!("Successfully updated SteamID for <@{id}>! New SteamID: `{steam_id}`")
+		}
+		Update::Created => format!("Successfully set SteamID `{steam_id}` for <@{id}>!"),
+	};
 
-	let updated = match ctx.fetch_user_by_id(id).await {
+	ctx.say(message).await?;
+
+	Ok(())
+}
+
+/// Saves `steam_id` for the user with the given `discord_id`.
+pub async fn set_steam_id(
+	users: &impl UserRepository,
+	name: &str,
+	discord_id: u64,
+	steam_id: SteamID,
+) -> Result<Update> {
+	let update = match users.get(discord_id).await? {
 		// User already has a database entry -> modify the current one
-		Some(user) => {
-			if user.steam_id.as_ref() == Some(&steam_id) {
-				// The user already has a SteamID set and tried to set it to the same value.
-				// :tf:
-				ctx.say("<:tf:999383331647012935>")
-					.await?;
-				return Ok(());
-			}
-
-			let mut query = QueryBuilder::new(format!(
-				r#"
-				UPDATE {table_name}
-				SET steam_id =
-				"#
-			));
-
-			query
-				.push_bind(steam_id.as_id32() as i32)
-				.push(" WHERE discord_id = ")
-				.push_bind(id as i64);
-
-			query.build().execute(ctx.db()).await?;
-
-			true
-		}
+		Some(user) if user.steam_id == Some(steam_id) => return Ok(Update::Unchanged),
+		Some(_) => Update::Updated,
 
 		// The user does not yet have an entry -> create one
-		None => {
-			let mut query = QueryBuilder::new(format!(
-				r#"
-				INSERT INTO {table_name}
-				    (name, discord_id, steam_id)
-				"#
-			));
-
-			query.push_values([(name, id, steam_id)], |mut query, (name, id, steam_id)| {
-				query
-					.push_bind(name)
-					.push_bind(id as i64)
-					.push_bind(steam_id.as_id32() as i32);
-			});
-
-			query.build().execute(ctx.db()).await?;
-
-			// We did not update an entry, but created one
-			false
-		}
+		None => Update::Created,
 	};
 
-	ctx.say(match updated {
-		true => format!("Successfully updated SteamID for <@{id}>! New SteamID: `{steam_id}`"),
-		false => format!("Successfully set SteamID `{steam_id}` for <@{id}>!"),
-	})
-	.await?;
+	// This also resets the verification if there was one, since it was for the old account.
+	users
+		.set_steam_id(name, discord_id, steam_id)
+		.await?;
 
-	Ok(())
+	Ok(update)
+}
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			commands::mode,
+			database::{InMemoryUserRepository, User},
+		},
+	};
+
+	#[tokio::test]
+	async fn create_and_update() {
+		let users = InMemoryUserRepository::default();
+		let steam_id = SteamID::from_id32(322356345);
+
+		let update = set_steam_id(&users, "AlphaKeks", 1, steam_id).await;
+		assert_eq!(update, Ok(Update::Created));
+		assert_eq!(
+			users
+				.get(1)
+				.await
+				.unwrap()
+				.unwrap()
+				.steam_id,
+			Some(steam_id)
+		);
+
+		let update = set_steam_id(&users, "AlphaKeks", 1, steam_id).await;
+		assert_eq!(update, Ok(Update::Unchanged));
+
+		let new_steam_id = SteamID::from_id32(1);
+		let update = set_steam_id(&users, "AlphaKeks", 1, new_steam_id).await;
+		assert_eq!(update, Ok(Update::Updated));
+		assert_eq!(
+			users
+				.get_by_steam_id(new_steam_id)
+				.await
+				.unwrap()
+				.unwrap()
+				.discord_id,
+			1
+		);
+	}
+
+	#[tokio::test]
+	async fn resets_verification() {
+		let users = InMemoryUserRepository::default();
+		let mut user = User::new("AlphaKeks", 1);
+		user.steam_id = Some(SteamID::from_id32(322356345));
+		user.verified = true;
+		users.insert(&user);
+
+		let update = set_steam_id(&users, "AlphaKeks", 1, SteamID::from_id32(1)).await;
+		assert_eq!(update, Ok(Update::Updated));
+		assert!(
+			!users
+				.get(1)
+				.await
+				.unwrap()
+				.unwrap()
+				.verified
+		);
+	}
+
+	#[tokio::test]
+	async fn keeps_mode() {
+		let users = InMemoryUserRepository::default();
+		mode::set_mode(&users, "AlphaKeks", 1, Some(gokz_rs::Mode::SimpleKZ))
+			.await
+			.unwrap();
+
+		let update = set_steam_id(&users, "AlphaKeks", 1, SteamID::from_id32(322356345)).await;
+		assert_eq!(update, Ok(Update::Updated));
+		assert_eq!(
+			users
+				.get(1)
+				.await
+				.unwrap()
+				.unwrap()
+				.mode,
+			Some(gokz_rs::Mode::SimpleKZ)
+		);
+	}
 }
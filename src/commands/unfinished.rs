@@ -1,15 +1,21 @@
 use {
 	super::{
-		custom_params::{ModeChoice, RuntypeChoice, TierChoice},
+		autocomplete,
+		custom_params::{BoolChoice, ModeChoice, RuntypeChoice, TierChoice},
 		pagination,
 	},
 	crate::{
+		api_cache::Endpoint,
 		error::{Error, Result},
 		state::{Context, StateContainer},
 		target::Target,
+		utils,
 	},
-	gokz_rs::{global_api, kzgo_api, schnose_api, Tier},
+	gokz_rs::{Mode, SteamID, Tier},
 	poise::serenity_prelude::CreateEmbed,
+	schnosebot::global_map::GlobalMap,
+	serde::Deserialize,
+	std::collections::HashSet,
 };
 
 /// Check which maps you still need to finish.
@@ -18,17 +24,20 @@ use {
 /// specify the following parameters:
 ///
 /// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-///     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-///     preference in the database, see `/mode`.
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
 /// - `runtype`: `TP` / `PRO`
 ///   - If you don't specify this, the bot will default to `PRO`.
 /// - `tier`: If you don't specify this, the bot will fetch maps for all tiers.
+/// - `bonuses`: also list bonuses you haven't finished yet, e.g. `kz_erratum_v2 B2 (T4)`.
 /// - `player`: this can be any string. The bot will try its best to interpret it as something \
 ///   useful. If you want to help it with that, specify one of the following:
 ///   - a `SteamID`, e.g. `STEAM_1:1:161178172`, `U:1:322356345` or `76561198282622073`
+///   - a link to a Steam or KZ:GO profile, e.g. `https://steamcommunity.com/id/alphakeks`
 ///   - a `Mention`, e.g. `@MyBestFriend`
-///   - a player's name, e.g. `AlphaKeks`
+///   - a player's name, e.g. `AlphaKeks`. If several players match, the bot will ask you \
+///     which one you mean.
 ///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
 ///     find one, or you don't have a SteamID set, the command will fail. To save a mode \
 ///     preference in the database, see `/setsteam`.
@@ -51,9 +60,14 @@ pub async fn unfinished(
 
 	#[description = "The player you want to target."]
 	#[rename = "player"]
+	#[autocomplete = "autocomplete::player_name"]
 	target: Option<String>,
+
+	#[description = "Also list unfinished bonuses"]
+	#[rename = "bonuses"]
+	bonuses_choice: Option<BoolChoice>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let mode = match mode_choice {
 		Some(choice) => choice.into(),
@@ -67,33 +81,68 @@ pub async fn unfinished(
 		Some(target) => target.parse()?,
 	};
 
-	let player_identifier = target.into_player(&ctx).await;
-
-	let player = schnose_api::get_player(player_identifier.clone(), ctx.gokz_client()).await?;
-
-	let unfinished = global_api::get_unfinished(
-		player_identifier,
-		mode,
-		runtype,
-		tier_choice.map(Tier::from),
-		ctx.gokz_client(),
-	)
-	.await?
-	.map(|maps| {
-		maps.into_iter()
-			.map(|map| {
-				if tier_choice.is_some() {
-					map.name
-				} else {
-					format!("{} (T{})", map.name, map.difficulty as u8)
-				}
-			})
-			.collect::<Vec<_>>()
-	});
+	let player_identifier = target.into_player(&ctx).await?;
+
+	let player = ctx
+		.api_cache()
+		.get_player(&player_identifier, ctx.gokz_client())
+		.await?;
+
+	ctx.recent_players()
+		.remember(&player.name, player.steam_id);
+
+	let tier = tier_choice.map(Tier::from);
+	let unfinished = ctx
+		.api_cache()
+		.get_unfinished(&player_identifier, mode, runtype, tier, ctx.gokz_client())
+		.await?
+		.map(|maps| {
+			maps.into_iter()
+				.map(|map| {
+					if tier_choice.is_some() {
+						map.name
+					} else {
+						format!("{} (T{})", map.name, map.difficulty as u8)
+					}
+				})
+				.collect::<Vec<_>>()
+		});
+
+	let bonuses = match bonuses_choice {
+		Some(BoolChoice::Yes) => {
+			let finished = finished_courses(&ctx, player.steam_id, mode, runtype).await?;
+			let maps = ctx.maps();
+
+			unfinished_bonuses(
+				&bonus_courses(&maps, mode),
+				&finished,
+				tier_choice.map(|tier| tier as u8),
+			)
+		}
+		_ => Vec::new(),
+	};
 
-	let avatar = kzgo_api::get_avatar(player.steam_id, ctx.gokz_client())
+	let count = match (unfinished.as_ref().map_or(0, Vec::len), bonuses.len()) {
+		(maps, 0) => format!("{maps} maps"),
+		(0, bonuses) => format!("{bonuses} bonuses"),
+		(maps, bonuses) => format!("{maps} maps & {bonuses} bonuses"),
+	};
+
+	let unfinished = if bonuses.is_empty() {
+		unfinished
+	} else {
+		Some(
+			unfinished
+				.unwrap_or_default()
+				.into_iter()
+				.chain(bonuses)
+				.collect(),
+		)
+	};
+
+	let avatar = ctx
+		.fetch_avatar(player.steam_id)
 		.await
-		.map(|user| user.avatar_url)
 		.unwrap_or_default();
 
 	let mut template = CreateEmbed::default()
@@ -142,13 +191,12 @@ pub async fn unfinished(
 		Some(maps) => {
 			let mut embeds = Vec::new();
 			let chunk_size = 10;
-			let len = maps.len();
 			let max_pages = (maps.len() as f64 / chunk_size as f64).ceil() as u8;
 			for (page_idx, map_names) in maps.chunks(chunk_size).enumerate() {
 				let mut temp = template.clone();
 				temp.title(format!(
-					"{} maps - {} {} {}",
-					len,
+					"{} - {} {} {}",
+					count,
 					mode.short(),
 					if runtype { "TP" } else { "PRO" },
 					tier_choice.map_or_else(String::new, |tier| format!("[T{}]", tier as u8))
@@ -173,3 +221,115 @@ pub async fn unfinished(
 
 	Ok(())
 }
+
+/// The parts of a bonus course that [`unfinished_bonuses`] cares about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bonus<'a> {
+	map_name: &'a str,
+	stage: u8,
+	tier: u8,
+}
+
+/// Every bonus on `maps` that have a filter for `mode`.
+fn bonus_courses(maps: &[GlobalMap], mode: Mode) -> Vec<Bonus<'_>> {
+	maps.iter()
+		.filter(|map| utils::has_filter(map, mode))
+		.flat_map(|map| {
+			map.courses
+				.iter()
+				.filter(|course| course.stage != 0)
+				.map(|course| Bonus {
+					map_name: &map.name,
+					stage: course.stage,
+					tier: course.tier as u8,
+				})
+		})
+		.collect()
+}
+
+/// The bonuses that aren't in `finished`, e.g. `kz_erratum_v2 B2 (T4)`. If a `tier` is given, only
+/// bonuses of that tier are listed and the tier is left out.
+fn unfinished_bonuses(
+	bonuses: &[Bonus<'_>],
+	finished: &HashSet<(String, u8)>,
+	tier: Option<u8>,
+) -> Vec<String> {
+	bonuses
+		.iter()
+		.filter(|bonus| tier.map_or(true, |tier| bonus.tier == tier))
+		.filter(|bonus| !finished.contains(&(bonus.map_name.to_owned(), bonus.stage)))
+		.map(|bonus| match tier {
+			Some(_) => format!("{} B{}", bonus.map_name, bonus.stage),
+			None => format!("{} B{} (T{})", bonus.map_name, bonus.stage, bonus.tier),
+		})
+		.collect()
+}
+
+/// A record as returned by the GlobalAPI's `/records/top` endpoint. We only care about where it
+/// was set.
+#[derive(Debug, Clone, Deserialize)]
+struct FinishedCourse {
+	map_name: String,
+	stage: u8,
+}
+
+/// Every `(map, course)` the player has finished, including bonuses.
+async fn finished_courses(
+	ctx: &Context<'_>,
+	steam_id: SteamID,
+	mode: Mode,
+	runtype: bool,
+) -> Result<HashSet<(String, u8)>> {
+	let steam_id = steam_id.as_id64().to_string();
+
+	let records = ctx
+		.api_cache()
+		.get_global_api::<Vec<FinishedCourse>>(
+			Endpoint::FinishedCourses,
+			&ctx.config().global_api_url,
+			"/records/top",
+			&[
+				("steamid64", steam_id.as_str()),
+				("modes_list_string", utils::api_mode(mode)),
+				("has_teleports", if runtype { "true" } else { "false" }),
+				("tickrate", "128"),
+				("limit", "9999"),
+			],
+			ctx.gokz_client(),
+		)
+		.await?;
+
+	Ok(records
+		.into_iter()
+		.map(|record| (record.map_name, record.stage))
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn erratum(stage: u8, tier: u8) -> Bonus<'static> {
+		Bonus { map_name: "kz_erratum_v2", stage, tier }
+	}
+
+	#[test]
+	fn unfinished() {
+		let bonuses = [
+			erratum(1, 3),
+			erratum(2, 4),
+			erratum(3, 4),
+		];
+		let finished = HashSet::from([
+			(String::from("kz_erratum_v2"), 0),
+			(String::from("kz_erratum_v2"), 3),
+		]);
+
+		assert_eq!(
+			unfinished_bonuses(&bonuses, &finished, None),
+			["kz_erratum_v2 B1 (T3)", "kz_erratum_v2 B2 (T4)"]
+		);
+		assert_eq!(unfinished_bonuses(&bonuses, &finished, Some(4)), ["kz_erratum_v2 B2"]);
+		assert!(unfinished_bonuses(&bonuses, &finished, Some(5)).is_empty());
+	}
+}
@@ -0,0 +1,153 @@
+use crate::{
+	database::UserRepository,
+	error::{Error, Result},
+	state::{Context, StateContainer},
+	steam::{self, SteamClient},
+	target::Target,
+};
+
+/// Prove that your SteamID belongs to you.
+///
+/// `/setsteam` accepts any SteamID, so server leaderboards, competitions and PB notifications \
+/// only consider SteamIDs that have been verified. Verifying works in two steps:
+///
+/// 1. Use this command. The bot will give you a code, e.g. `schnose-123456`.
+/// 2. Put that code anywhere in your Steam name and use this command again.
+///
+/// Once your SteamID is verified, you can change your Steam name back. If you change your \
+/// SteamID with `/setsteam`, you will have to verify the new one.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
+pub async fn verify(ctx: Context<'_>) -> Result<()> {
+	ctx.defer_ephemeral().await?;
+
+	let message = match verify_user(ctx.users(), ctx.steam(), ctx.author_id()).await? {
+		Verification::AlreadyVerified => String::from("Your SteamID is already verified."),
+		Verification::Pending { code } => format!(
+			"Please put `{code}` into your Steam name and use `/verify` again. It might take a \
+			 few minutes for Steam to notice the new name."
+		),
+		Verification::CodeNotFound { code } => format!(
+			"Your Steam name doesn't contain `{code}` yet. If you just changed it, please wait a \
+			 few minutes and try again."
+		),
+		Verification::Verified => String::from(
+			"Successfully verified your SteamID! You can change your Steam name back now.",
+		),
+	};
+
+	ctx.say(message).await?;
+
+	Ok(())
+}
+
+/// The outcome of [`verify_user`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+	/// There was nothing to do.
+	AlreadyVerified,
+
+	/// A new code was created, which the user now has to put into their Steam name.
+	Pending { code: String },
+
+	/// The user's Steam name doesn't contain their code yet.
+	CodeNotFound { code: String },
+
+	/// The user's Steam name contained their code.
+	Verified,
+}
+
+/// Runs the next step of the verification for the user with the given `discord_id`.
+pub async fn verify_user(
+	users: &impl UserRepository,
+	steam: &impl SteamClient,
+	discord_id: u64,
+) -> Result<Verification> {
+	let user = users
+		.get(discord_id)
+		.await?
+		.ok_or(Error::UserNotInDatabase {
+			user: Target::None { user_id: discord_id },
+		})?;
+
+	let steam_id = user.steam_id.ok_or(Error::NoSteamID)?;
+
+	if user.verified {
+		return Ok(Verification::AlreadyVerified);
+	}
+
+	let Some(code) = user.verification_code else {
+		let code = steam::verification_code();
+		users
+			.set_verification(discord_id, false, Some(&code))
+			.await?;
+
+		return Ok(Verification::Pending { code });
+	};
+
+	if !steam::check_code(steam, steam_id, &code).await? {
+		return Ok(Verification::CodeNotFound { code });
+	}
+
+	users
+		.set_verification(discord_id, true, None)
+		.await?;
+
+	Ok(Verification::Verified)
+}
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			database::{InMemoryUserRepository, User},
+			steam::MockSteamClient,
+		},
+		gokz_rs::SteamID,
+	};
+
+	#[tokio::test]
+	async fn verification_flow() {
+		let users = InMemoryUserRepository::default();
+		let steam = MockSteamClient::default();
+		let steam_id = SteamID::from_id32(322356345);
+
+		assert_eq!(
+			verify_user(&users, &steam, 1).await,
+			Err(Error::UserNotInDatabase { user: Target::None { user_id: 1 } })
+		);
+
+		users.insert(&User::new("AlphaKeks", 1));
+		assert_eq!(verify_user(&users, &steam, 1).await, Err(Error::NoSteamID));
+
+		users
+			.set_steam_id("AlphaKeks", 1, steam_id)
+			.await
+			.unwrap();
+		steam.set_name(steam_id, "AlphaKeks");
+
+		let Ok(Verification::Pending { code }) = verify_user(&users, &steam, 1).await else {
+			panic!("Expected a new verification code.");
+		};
+
+		// Asking again shouldn't create a new code.
+		assert_eq!(
+			verify_user(&users, &steam, 1).await,
+			Ok(Verification::CodeNotFound { code: code.clone() })
+		);
+
+		steam.set_name(steam_id, &format!("AlphaKeks {code}"));
+		assert_eq!(verify_user(&users, &steam, 1).await, Ok(Verification::Verified));
+		assert!(
+			users
+				.get(1)
+				.await
+				.unwrap()
+				.unwrap()
+				.verified
+		);
+
+		assert_eq!(verify_user(&users, &steam, 1).await, Ok(Verification::AlreadyVerified));
+	}
+}
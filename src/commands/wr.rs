@@ -4,7 +4,6 @@ use {
 		error::{Error, Result},
 		state::{Context, StateContainer},
 	},
-	gokz_rs::global_api,
 	schnosebot::time,
 };
 
@@ -14,9 +13,9 @@ use {
 /// `map` and may also specify the following options:
 ///
 /// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
-///   - If you don't specify this, the bot will search the database for your UserID. If it can't \
-///     find one, or you don't have a mode preference set, the command will fail. To save a mode \
-///     preference in the database, see `/mode`.
+///   - If you don't specify this, the bot will search the database for your UserID and use your \
+///     mode preference. To save a mode preference in the database, see `/mode`. If you don't \
+///     have one, the server's default mode is used (see `/config`), or `KZTimer` if there is none.
 #[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
 #[poise::command(slash_command, ephemeral, on_error = "Error::handle")]
 pub async fn wr(
@@ -31,22 +30,29 @@ pub async fn wr(
 	#[rename = "mode"]
 	mode_choice: Option<ModeChoice>,
 ) -> Result<()> {
-	ctx.defer().await?;
+	ctx.defer_reply().await?;
 
 	let map = ctx.get_map(map_choice.clone())?;
+	ctx.recent_maps()
+		.remember(ctx.author_id(), &map.name);
 
 	let mode = match mode_choice {
 		Some(choice) => choice.into(),
 		None => ModeChoice::figure_out(ctx.author_id().into(), &ctx).await,
 	};
 
-	let tp_wr = global_api::get_wr(map.name.clone().into(), mode, true, 0, ctx.gokz_client()).await;
+	let tp_wr = ctx
+		.api_cache()
+		.get_wr(&map.name, mode, true, 0, ctx.gokz_client())
+		.await;
 
-	let pro_wr =
-		global_api::get_wr(map.name.clone().into(), mode, false, 0, ctx.gokz_client()).await;
+	let pro_wr = ctx
+		.api_cache()
+		.get_wr(&map.name, mode, false, 0, ctx.gokz_client())
+		.await;
 
-	if tp_wr.is_err() && pro_wr.is_err() {
-		return Err(Error::NoRecords);
+	if let (Err(why), Err(_)) = (&tp_wr, &pro_wr) {
+		return Err(why.clone().or_no_records());
 	}
 
 	let (tp_time, tp_links) = match &tp_wr {
@@ -0,0 +1,122 @@
+use {
+	super::custom_params::{BoolChoice, ModeChoice, RuntypeChoice, TierChoice},
+	crate::{
+		error::{Error, Result},
+		state::{Context, StateContainer},
+	},
+	poise::serenity_prelude::Channel,
+	sqlx::QueryBuilder,
+};
+
+/// Subscribe a channel to new world records.
+///
+/// This command will make the bot post an embed in a channel of your choice whenever a new world \
+/// record is set on any main course. You need the `Manage Server` permission to use this command. \
+/// Every channel can only have one subscription, so using this command again on the same channel \
+/// will overwrite its filters. You are required to specify a `channel` and may also specify the \
+/// following options:
+///
+/// - `mode`: `KZTimer` / `SimpleKZ` / `Vanilla`
+///   - If you don't specify this, records in all modes will be posted.
+/// - `runtype`: `TP` / `PRO`
+///   - If you don't specify this, both TP and PRO records will be posted.
+/// - `tier`: the minimum tier a map needs to have for its records to be posted.
+///   - If you don't specify this, records on all tiers will be posted.
+/// - `unsubscribe`: set this to `Yes` to stop posting world records in `channel`.
+#[tracing::instrument(skip(ctx), fields(user = ctx.author().tag()))]
+#[poise::command(
+	slash_command,
+	guild_only,
+	ephemeral,
+	required_permissions = "MANAGE_GUILD",
+	default_member_permissions = "MANAGE_GUILD",
+	on_error = "Error::handle"
+)]
+pub async fn wrfeed(
+	ctx: Context<'_>,
+
+	#[description = "The channel to post new world records in."] channel: Channel,
+
+	#[description = "KZT/SKZ/VNL"]
+	#[rename = "mode"]
+	mode_choice: Option<ModeChoice>,
+
+	#[description = "TP/PRO"]
+	#[rename = "runtype"]
+	runtype_choice: Option<RuntypeChoice>,
+
+	#[description = "Only post records on maps of this tier or harder."]
+	#[rename = "tier"]
+	tier_choice: Option<TierChoice>,
+
+	#[description = "Stop posting world records in this channel."] unsubscribe: Option<BoolChoice>,
+) -> Result<()> {
+	ctx.defer().await?;
+
+	let channel_id = channel.id();
+	let guild_id = ctx
+		.guild_id()
+		.ok_or(Error::Custom(String::from("This command only works on servers.")))?;
+
+	if unsubscribe.map_or(false, bool::from) {
+		let result = sqlx::query("DELETE FROM wr_feed_subscriptions WHERE channel_id = $1")
+			.bind(*channel_id.as_u64() as i64)
+			.execute(ctx.db())
+			.await?;
+
+		ctx.say(match result.rows_affected() {
+			0 => format!("<#{channel_id}> is not subscribed to the world record feed."),
+			_ => format!("Successfully unsubscribed <#{channel_id}> from the world record feed."),
+		})
+		.await?;
+
+		return Ok(());
+	}
+
+	let mut query = QueryBuilder::new(
+		"INSERT INTO wr_feed_subscriptions (channel_id, guild_id, mode, runtype, tier) ",
+	);
+
+	query
+		.push_values(
+			[(channel_id, guild_id, mode_choice, runtype_choice, tier_choice)],
+			|mut query, (channel_id, guild_id, mode, runtype, tier)| {
+				query
+					.push_bind(*channel_id.as_u64() as i64)
+					.push_bind(*guild_id.as_u64() as i64)
+					.push_bind(mode.map(|mode| mode as u8 as i16))
+					.push_bind(runtype.map(bool::from))
+					.push_bind(tier.map(|tier| tier as u8 as i16));
+			},
+		)
+		.push(
+			r#"
+			ON CONFLICT (channel_id) DO UPDATE SET
+			    mode = EXCLUDED.mode,
+			    runtype = EXCLUDED.runtype,
+			    tier = EXCLUDED.tier
+			"#,
+		);
+
+	query.build().execute(ctx.db()).await?;
+
+	let filters = [
+		mode_choice.map(|mode| gokz_rs::Mode::from(mode).short()),
+		runtype_choice.map(|runtype| String::from(if bool::from(runtype) { "TP" } else { "PRO" })),
+		tier_choice.map(|tier| format!("T{}+", tier as u8)),
+	]
+	.into_iter()
+	.flatten()
+	.collect::<Vec<_>>();
+
+	ctx.say(match filters.is_empty() {
+		true => format!("Successfully subscribed <#{channel_id}> to all new world records!"),
+		false => format!(
+			"Successfully subscribed <#{channel_id}> to new world records! Filters: `{}`",
+			filters.join(", ")
+		),
+	})
+	.await?;
+
+	Ok(())
+}
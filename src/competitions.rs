@@ -0,0 +1,710 @@
+//! Community competitions on a single map.
+//!
+//! Server admins create a competition with `/competition create`. Once it starts, we remember the
+//! personal bests of every verified member of that server, post a standings message and regularly
+//! check whether anyone set a new personal best on the map. Members who verify while the
+//! competition is running join on the next check. The standings message is edited in place and
+//! the winners are announced once the competition is over. Everything lives in the
+//! database, so restarting the bot in the middle of a competition doesn't lose anything.
+
+use {
+	crate::{
+		error::{Error, Result},
+		state::{self, State},
+	},
+	chrono::{DateTime, Utc},
+	gokz_rs::{Mode, PlayerIdentifier, SteamID},
+	poise::serenity_prelude::{ChannelId, CreateEmbed, GuildId, Http, MessageId},
+	schnosebot::time,
+	sqlx::{FromRow, Pool, Postgres},
+	std::{
+		collections::{HashMap, HashSet},
+		sync::Arc,
+		time::Duration,
+	},
+	tracing::{error, info, trace},
+};
+
+#[derive(Debug, Clone, FromRow)]
+pub struct CompetitionRow {
+	id: i64,
+	guild_id: i64,
+	channel_id: i64,
+	message_id: Option<i64>,
+	map_name: String,
+	mode: i16,
+	runtype: bool,
+	starts_at: DateTime<Utc>,
+	ends_at: DateTime<Utc>,
+	started: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Competition {
+	pub id: i64,
+	pub guild_id: u64,
+
+	/// Channel the standings and winners are posted in
+	pub channel_id: u64,
+
+	/// The live standings message; `None` until the competition starts
+	pub message_id: Option<u64>,
+
+	pub map_name: String,
+	pub mode: Mode,
+	pub runtype: bool,
+	pub starts_at: DateTime<Utc>,
+	pub ends_at: DateTime<Utc>,
+
+	/// Whether we already saved everyone's personal bests from before the competition
+	pub started: bool,
+}
+
+impl TryFrom<CompetitionRow> for Competition {
+	type Error = Error;
+
+	fn try_from(row: CompetitionRow) -> Result<Self> {
+		Ok(Self {
+			id: row.id,
+			guild_id: row.guild_id as u64,
+			channel_id: row.channel_id as u64,
+			message_id: row.message_id.map(|id| id as u64),
+			map_name: row.map_name,
+			mode: Mode::try_from(u8::try_from(row.mode)?)?,
+			runtype: row.runtype,
+			starts_at: row.starts_at,
+			ends_at: row.ends_at,
+			started: row.started,
+		})
+	}
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct EntryRow {
+	steam_id: i64,
+	discord_id: i64,
+	player_name: String,
+	start_time: Option<f64>,
+	best_time: Option<f64>,
+	best_teleports: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+	pub steam_id: SteamID,
+	pub discord_id: u64,
+	pub player_name: String,
+
+	/// The player's personal best from before the competition started
+	pub start_time: Option<f64>,
+
+	/// The best time the player set during the competition
+	pub best_time: Option<f64>,
+	pub best_teleports: Option<u32>,
+}
+
+impl TryFrom<EntryRow> for Entry {
+	type Error = Error;
+
+	fn try_from(row: EntryRow) -> Result<Self> {
+		Ok(Self {
+			steam_id: SteamID::from_id32(u32::try_from(row.steam_id)?),
+			discord_id: row.discord_id as u64,
+			player_name: row.player_name,
+			start_time: row.start_time,
+			best_time: row.best_time,
+			best_teleports: row
+				.best_teleports
+				.map(u32::try_from)
+				.transpose()?,
+		})
+	}
+}
+
+/// Everyone who set a time during the competition, fastest first.
+pub fn standings(entries: &[Entry]) -> Vec<&Entry> {
+	let mut standings = entries
+		.iter()
+		.filter(|entry| entry.best_time.is_some())
+		.collect::<Vec<_>>();
+
+	standings.sort_by(|a, b| {
+		a.best_time
+			.unwrap_or_default()
+			.total_cmp(&b.best_time.unwrap_or_default())
+	});
+
+	standings
+}
+
+/// `🥇`, `🥈`, `🥉` or `#4` etc.
+pub fn medal(place: usize) -> String {
+	match place {
+		1 => String::from("🥇"),
+		2 => String::from("🥈"),
+		3 => String::from("🥉"),
+		place => format!("#{place}"),
+	}
+}
+
+/// Creates a new competition. Every server can only have one competition that hasn't finished yet.
+#[allow(clippy::too_many_arguments)]
+pub async fn create(
+	guild_id: u64,
+	channel_id: u64,
+	map_name: &str,
+	mode: Mode,
+	runtype: bool,
+	starts_at: DateTime<Utc>,
+	ends_at: DateTime<Utc>,
+	db: &Pool<Postgres>,
+) -> Result<Competition> {
+	sqlx::query_as::<_, CompetitionRow>(
+		r#"
+		INSERT INTO competitions (guild_id, channel_id, map_name, mode, runtype, starts_at, ends_at)
+		VALUES ($1, $2, $3, $4, $5, $6, $7)
+		RETURNING *
+		"#,
+	)
+	.bind(guild_id as i64)
+	.bind(channel_id as i64)
+	.bind(map_name)
+	.bind(mode as u8 as i16)
+	.bind(runtype)
+	.bind(starts_at)
+	.bind(ends_at)
+	.fetch_one(db)
+	.await
+	.map_err(|why| match &why {
+		// Two admins might create a competition at the same time.
+		sqlx::Error::Database(error)
+			if error.constraint() == Some("competitions_one_per_guild") =>
+		{
+			Error::CompetitionAlreadyRunning
+		}
+		_ => Error::from(why),
+	})?
+	.try_into()
+}
+
+/// The competition of a server that hasn't finished yet, if there is one.
+pub async fn fetch_active(guild_id: u64, db: &Pool<Postgres>) -> Result<Option<Competition>> {
+	sqlx::query_as::<_, CompetitionRow>(
+		"SELECT * FROM competitions WHERE guild_id = $1 AND NOT finished",
+	)
+	.bind(guild_id as i64)
+	.fetch_optional(db)
+	.await?
+	.map(Competition::try_from)
+	.transpose()
+}
+
+/// All competitions that haven't finished yet, across all servers.
+pub async fn fetch_unfinished(db: &Pool<Postgres>) -> Result<Vec<Competition>> {
+	sqlx::query_as::<_, CompetitionRow>("SELECT * FROM competitions WHERE NOT finished")
+		.fetch_all(db)
+		.await?
+		.into_iter()
+		.map(Competition::try_from)
+		.collect()
+}
+
+pub async fn fetch_entries(competition_id: i64, db: &Pool<Postgres>) -> Result<Vec<Entry>> {
+	sqlx::query_as::<_, EntryRow>("SELECT * FROM competition_entries WHERE competition_id = $1")
+		.bind(competition_id)
+		.fetch_all(db)
+		.await?
+		.into_iter()
+		.map(Entry::try_from)
+		.collect()
+}
+
+/// Moves the end of a running competition to now. The background task will pick it up and
+/// announce the winners.
+pub async fn end_now(competition_id: i64, db: &Pool<Postgres>) -> Result<()> {
+	sqlx::query("UPDATE competitions SET ends_at = NOW() WHERE id = $1 AND started")
+		.bind(competition_id)
+		.execute(db)
+		.await?;
+
+	Ok(())
+}
+
+/// Deletes a competition without announcing any winners.
+pub async fn cancel(competition_id: i64, db: &Pool<Postgres>) -> Result<()> {
+	sqlx::query("DELETE FROM competitions WHERE id = $1")
+		.bind(competition_id)
+		.execute(db)
+		.await?;
+
+	Ok(())
+}
+
+/// The standings embed that gets posted in the competition's channel and by
+/// `/competition standings`.
+pub fn standings_embed(
+	competition: &Competition,
+	entries: &[Entry],
+	finished: bool,
+	state: &State,
+) -> CreateEmbed {
+	let runtype = if competition.runtype { "TP" } else { "PRO" };
+
+	let (url, thumbnail) = state
+		.global_maps
+		.maps()
+		.iter()
+		.find(|map| map.name == competition.map_name)
+		.map(|map| {
+			(
+				format!("{}?{}=", map.kzgo_link(), competition.mode.short().to_lowercase()),
+				map.thumbnail(),
+			)
+		})
+		.unwrap_or_else(|| (String::new(), String::from("https://kzgo.eu/kz_default.png")));
+
+	let standings = standings(entries);
+
+	let mut embed = CreateEmbed::default();
+	embed
+		.color(state.color)
+		.title(format!(
+			"[{} {runtype}] {} on {}",
+			competition.mode.short(),
+			if finished { "Final standings" } else { "Competition" },
+			competition.map_name
+		))
+		.url(url)
+		.thumbnail(thumbnail)
+		.description(format!(
+			"{} <t:{}:R> · {} players\n{}",
+			if finished { "Ended" } else { "Ends" },
+			competition.ends_at.timestamp(),
+			entries.len(),
+			if standings.is_empty() { "Nobody has set a time yet." } else { "" }
+		))
+		.footer(|footer| {
+			footer
+				.text(format!("{} | Competition #{}", state.schnose, competition.id))
+				.icon_url(&state.icon_url)
+		});
+
+	// Embeds can't have more than 25 fields.
+	for (i, entry) in standings
+		.into_iter()
+		.take(25)
+		.enumerate()
+	{
+		let time = time::format(entry.best_time.unwrap_or_default());
+		let teleports = match entry.best_teleports.unwrap_or_default() {
+			0 => String::new(),
+			1 => String::from(" (1 TP)"),
+			n => format!(" ({n} TPs)"),
+		};
+		let previous = match entry.start_time {
+			None => String::from("First completion!"),
+			Some(start_time) => format!("Before: {}", time::format(start_time)),
+		};
+
+		embed.field(
+			format!("{} {}", medal(i + 1), entry.player_name),
+			format!("{time}{teleports}\n{previous}"),
+			true,
+		);
+	}
+
+	embed
+}
+
+/// How often we try to finish a competition that is already over before giving up on it.
+const MAX_FINISH_ATTEMPTS: u32 = 12;
+
+/// Spawns the background task that starts, tracks and ends competitions.
+pub fn spawn(http: Arc<Http>, state: State) {
+	tokio::spawn(async move {
+		let mut interval =
+			tokio::time::interval(Duration::from_secs(state.config.competition_interval));
+
+		// How often finishing each competition failed in a row.
+		let mut failed_attempts = HashMap::<i64, u32>::new();
+
+		loop {
+			interval.tick().await;
+
+			trace!("Checking competitions.");
+
+			let competitions = match fetch_unfinished(&state.database_connection).await {
+				Ok(competitions) => competitions,
+				Err(why) => {
+					error!("Failed to fetch competitions: {why:?}");
+					continue;
+				}
+			};
+
+			for competition in competitions {
+				let id = competition.id;
+				let over = Utc::now() >= competition.ends_at;
+
+				let why = match update(&http, &state, competition).await {
+					Ok(()) => {
+						failed_attempts.remove(&id);
+						continue;
+					}
+					Err(why) => why,
+				};
+
+				error!("Failed to update competition `{id}`: {why:?}");
+
+				let give_up = match why {
+					// Without a way to post the standings there is no point in keeping it around.
+					Error::CompetitionChannelUnavailable => true,
+					_ if over => {
+						let attempts = failed_attempts.entry(id).or_default();
+						*attempts += 1;
+						*attempts >= MAX_FINISH_ATTEMPTS
+					}
+					_ => false,
+				};
+
+				if !give_up {
+					continue;
+				}
+
+				failed_attempts.remove(&id);
+
+				match finish(id, &state.database_connection).await {
+					Ok(()) => error!("Gave up on competition `{id}` and marked it as finished."),
+					Err(why) => error!("Failed to mark competition `{id}` as finished: {why:?}"),
+				}
+			}
+		}
+	});
+}
+
+async fn update(http: &Http, state: &State, mut competition: Competition) -> Result<()> {
+	let now = Utc::now();
+
+	if now < competition.starts_at {
+		return Ok(());
+	}
+
+	if competition.started {
+		add_participants(http, state, &competition).await?;
+	} else {
+		start(http, state, &competition).await?;
+		competition.started = true;
+	}
+
+	track(state, &competition).await?;
+
+	let db = &state.database_connection;
+	let entries = fetch_entries(competition.id, db).await?;
+	let finished = now >= competition.ends_at;
+
+	post_standings(http, state, &mut competition, &entries, finished).await?;
+
+	if finished {
+		announce_winners(http, state, &competition, &entries).await?;
+		finish(competition.id, db).await?;
+
+		info!("Competition `{}` on `{}` finished.", competition.id, competition.map_name);
+	}
+
+	Ok(())
+}
+
+async fn finish(competition_id: i64, db: &Pool<Postgres>) -> Result<()> {
+	sqlx::query("UPDATE competitions SET finished = TRUE WHERE id = $1")
+		.bind(competition_id)
+		.execute(db)
+		.await?;
+
+	Ok(())
+}
+
+/// Saves the current personal bests of every verified member of the competition's server.
+async fn start(http: &Http, state: &State, competition: &Competition) -> Result<()> {
+	let db = &state.database_connection;
+
+	add_participants(http, state, competition).await?;
+
+	sqlx::query("UPDATE competitions SET started = TRUE WHERE id = $1")
+		.bind(competition.id)
+		.execute(db)
+		.await?;
+
+	info!("Competition `{}` on `{}` started.", competition.id, competition.map_name);
+
+	Ok(())
+}
+
+/// Adds every verified member of the competition's server who isn't taking part yet, e.g. because
+/// they verified after the competition started.
+async fn add_participants(http: &Http, state: &State, competition: &Competition) -> Result<()> {
+	let db = &state.database_connection;
+	let guild_id = GuildId(competition.guild_id);
+
+	let participants = fetch_entries(competition.id, db)
+		.await?
+		.into_iter()
+		.map(|entry| entry.steam_id.as_id32())
+		.collect::<HashSet<_>>();
+
+	for user in state::fetch_verified_members(http, guild_id, &state.users).await? {
+		let Some(steam_id) = user
+			.steam_id
+			.filter(|steam_id| !participants.contains(&steam_id.as_id32()))
+		else {
+			continue;
+		};
+
+		let (player_name, start_time) = match fetch_pb(state, steam_id, competition).await {
+			// If someone already improved during the competition (e.g. because they verified late),
+			// we don't know their time from before. `track` picks up the new one.
+			Ok(pb) if pb.created_on >= competition.starts_at.timestamp() => (pb.player_name, None),
+			Ok(pb) => (pb.player_name, Some(pb.time)),
+			// We don't want to treat everyone as if they never finished the map just because the
+			// API is down, so we try again next time.
+			Err(why @ Error::ApiUnavailable { .. }) => return Err(why),
+			Err(_) => (user.name, None),
+		};
+
+		sqlx::query(
+			r#"
+			INSERT INTO competition_entries (competition_id, steam_id, discord_id, player_name, start_time)
+			VALUES ($1, $2, $3, $4, $5)
+			ON CONFLICT (competition_id, steam_id) DO NOTHING
+			"#,
+		)
+		.bind(competition.id)
+		.bind(steam_id.as_id32() as i64)
+		.bind(user.discord_id as i64)
+		.bind(player_name)
+		.bind(start_time)
+		.execute(db)
+		.await?;
+	}
+
+	Ok(())
+}
+
+/// Checks every participant for a new personal best that was set during the competition.
+async fn track(state: &State, competition: &Competition) -> Result<()> {
+	let db = &state.database_connection;
+	let window = competition.starts_at.timestamp()..=competition.ends_at.timestamp();
+
+	for entry in fetch_entries(competition.id, db).await? {
+		let pb = match fetch_pb(state, entry.steam_id, competition).await {
+			Ok(pb) => pb,
+			Err(why @ Error::ApiUnavailable { .. }) => return Err(why),
+			Err(_) => continue,
+		};
+
+		if !window.contains(&pb.created_on)
+			|| entry
+				.best_time
+				.map_or(false, |best_time| best_time <= pb.time)
+		{
+			continue;
+		}
+
+		sqlx::query(
+			r#"
+			UPDATE competition_entries
+			SET player_name = $3, best_time = $4, best_teleports = $5
+			WHERE competition_id = $1 AND steam_id = $2
+			"#,
+		)
+		.bind(competition.id)
+		.bind(entry.steam_id.as_id32() as i64)
+		.bind(&pb.player_name)
+		.bind(pb.time)
+		.bind(pb.teleports as i32)
+		.execute(db)
+		.await?;
+
+		trace!("`{}` improved to {} in competition `{}`.", pb.player_name, pb.time, competition.id);
+	}
+
+	Ok(())
+}
+
+/// The parts of a personal best we care about.
+struct Pb {
+	player_name: String,
+	time: f64,
+	teleports: u32,
+	created_on: i64,
+}
+
+async fn fetch_pb(state: &State, steam_id: SteamID, competition: &Competition) -> Result<Pb> {
+	let player: PlayerIdentifier = steam_id.into();
+
+	let pb = state
+		.api_cache
+		.get_pb(
+			&player, &competition.map_name, competition.mode, competition.runtype, 0,
+			&state.gokz_client,
+		)
+		.await?;
+
+	Ok(Pb {
+		player_name: pb.player_name,
+		time: pb.time,
+		teleports: pb.teleports as u32,
+		created_on: pb.created_on.timestamp(),
+	})
+}
+
+/// Edits the standings message, or posts a new one if there is none yet (or it got deleted).
+async fn post_standings(
+	http: &Http,
+	state: &State,
+	competition: &mut Competition,
+	entries: &[Entry],
+	finished: bool,
+) -> Result<()> {
+	let channel_id = ChannelId(competition.channel_id);
+	let embed = standings_embed(competition, entries, finished, state);
+
+	if let Some(message_id) = competition.message_id {
+		let edited = channel_id
+			.edit_message(http, MessageId(message_id), |message| {
+				message.embed(|e| {
+					*e = embed.clone();
+					e
+				})
+			})
+			.await;
+
+		if edited.is_ok() {
+			return Ok(());
+		}
+	}
+
+	let message = channel_id
+		.send_message(http, |message| message.set_embed(embed))
+		.await
+		.map_err(channel_error)?;
+
+	sqlx::query("UPDATE competitions SET message_id = $2 WHERE id = $1")
+		.bind(competition.id)
+		.bind(*message.id.as_u64() as i64)
+		.execute(&state.database_connection)
+		.await?;
+
+	competition.message_id = Some(*message.id.as_u64());
+
+	Ok(())
+}
+
+async fn announce_winners(
+	http: &Http,
+	state: &State,
+	competition: &Competition,
+	entries: &[Entry],
+) -> Result<()> {
+	let standings = standings(entries);
+
+	let winners = match standings.is_empty() {
+		true => String::from("Nobody set a time, so there are no winners this time. 😔"),
+		false => standings
+			.iter()
+			.take(3)
+			.enumerate()
+			.map(|(i, entry)| {
+				format!(
+					"{} <@{}> - {}",
+					medal(i + 1),
+					entry.discord_id,
+					time::format(entry.best_time.unwrap_or_default())
+				)
+			})
+			.collect::<Vec<_>>()
+			.join("\n"),
+	};
+
+	ChannelId(competition.channel_id)
+		.send_message(http, |message| {
+			message.embed(|embed| {
+				embed
+					.color(state.color)
+					.title(format!(
+						"[{} {}] Competition on {} is over!",
+						competition.mode.short(),
+						if competition.runtype { "TP" } else { "PRO" },
+						competition.map_name
+					))
+					.description(winners)
+					.footer(|footer| {
+						footer
+							.text(format!("{} | Competition #{}", state.schnose, competition.id))
+							.icon_url(&state.icon_url)
+					})
+			})
+		})
+		.await
+		.map_err(channel_error)?;
+
+	Ok(())
+}
+
+/// Discord answers with 403 / 404 if the channel was deleted or we lost access to it. Trying
+/// again won't help in that case.
+fn channel_error(why: serenity::Error) -> Error {
+	match &why {
+		serenity::Error::Http(http_error)
+			if matches!(
+				http_error
+					.status_code()
+					.map(|status_code| status_code.as_u16()),
+				Some(403 | 404)
+			) =>
+		{
+			Error::CompetitionChannelUnavailable
+		}
+		_ => Error::from(why),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(account_id: u32, start_time: Option<f64>, best_time: Option<f64>) -> Entry {
+		Entry {
+			steam_id: SteamID::from_id32(account_id),
+			discord_id: account_id as u64,
+			player_name: account_id.to_string(),
+			start_time,
+			best_time,
+			best_teleports: best_time.map(|_| 0),
+		}
+	}
+
+	#[test]
+	fn ranking() {
+		let entries = [
+			entry(1, Some(60.0), Some(55.0)),
+			entry(2, Some(50.0), None),
+			entry(3, None, Some(42.0)),
+			entry(4, Some(70.0), Some(58.5)),
+		];
+
+		let standings = standings(&entries)
+			.into_iter()
+			.map(|entry| entry.discord_id)
+			.collect::<Vec<_>>();
+
+		// Players who didn't set a time during the competition aren't ranked, no matter how fast
+		// they were before.
+		assert_eq!(standings, [3, 1, 4]);
+	}
+
+	#[test]
+	fn medals() {
+		assert_eq!(medal(1), "🥇");
+		assert_eq!(medal(3), "🥉");
+		assert_eq!(medal(4), "#4");
+	}
+}
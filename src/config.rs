@@ -1,4 +1,7 @@
-use shuttle_secrets::SecretStore;
+use {
+	std::{collections::HashMap, path::Path},
+	tracing::info,
+};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -8,9 +11,6 @@ pub struct Config {
 	/// PostgreSQL connection string
 	pub database_url: String,
 
-	/// PostgreSQL table name to store user information in
-	pub users_table: String,
-
 	/// `ChannelID` to send reports to
 	pub report_channel_id: u64,
 
@@ -22,40 +22,111 @@ pub struct Config {
 
 	/// `UserID` with special privileges
 	pub owner_id: u64,
+
+	/// How often the global map cache should be refreshed (in seconds)
+	pub map_refresh_interval: u64,
+
+	/// Base URL of the GlobalAPI
+	pub global_api_url: String,
+
+	/// How often the GlobalAPI should be polled for new world records (in seconds)
+	pub wr_feed_interval: u64,
+
+	/// How often users should be checked for new personal bests (in seconds)
+	pub pb_notification_interval: u64,
+
+	/// How often the GlobalAPI's health report should be checked (in seconds)
+	pub health_check_interval: u64,
+
+	/// How often the stats of linked users should be saved for `/progress` (in seconds)
+	pub progress_snapshot_interval: u64,
+
+	/// How often running competitions should be checked for new times (in seconds)
+	pub competition_interval: u64,
 }
 
 impl Config {
-	pub fn new(secret_store: &SecretStore) -> Self {
+	/// Reads the config from shuttle's secret store.
+	#[cfg(feature = "shuttle")]
+	pub fn new(secret_store: &shuttle_secrets::SecretStore) -> Self {
+		Self::from_source(|key| secret_store.get(key))
+	}
+
+	/// Reads the config from environment variables, using the same keys as
+	/// `Secrets.example.toml`.
+	pub fn from_env() -> Self {
+		Self::from_source(|key| std::env::var(key).ok())
+	}
+
+	/// Reads the config from a TOML file with the same keys as `Secrets.example.toml`.
+	/// Environment variables take precedence over values in the file.
+	pub fn from_file(path: impl AsRef<Path>) -> Self {
+		let path = path.as_ref();
+
+		info!("Reading config from `{}`.", path.display());
+
+		let file = std::fs::read_to_string(path)
+			.unwrap_or_else(|why| panic!("Failed to read `{}`: {why}", path.display()));
+
+		let values = toml::from_str::<HashMap<String, toml::Value>>(&file)
+			.unwrap_or_else(|why| panic!("Failed to parse `{}`: {why}", path.display()))
+			.into_iter()
+			.map(|(key, value)| {
+				let value = match value {
+					toml::Value::String(value) => value,
+					value => value.to_string(),
+				};
+
+				(key, value)
+			})
+			.collect::<HashMap<_, _>>();
+
+		Self::from_source(|key| {
+			std::env::var(key)
+				.ok()
+				.or_else(|| values.get(key).cloned())
+		})
+	}
+
+	fn from_source(get: impl Fn(&str) -> Option<String>) -> Self {
 		Self {
-			discord_token: secret_store
-				.get("DISCORD_TOKEN")
-				.expect("Missing `DISCORD_TOKEN` secret."),
-			database_url: secret_store
-				.get("DATABASE_URL")
-				.expect("Missing `DATABASE_URL` secret."),
-			users_table: secret_store
-				.get("USERS_TABLE")
-				.expect("Missing `USERS_TABLE` secret."),
-			report_channel_id: secret_store
-				.get("REPORT_CHANNEL_ID")
+			discord_token: get("DISCORD_TOKEN").expect("Missing `DISCORD_TOKEN` secret."),
+			database_url: get("DATABASE_URL").expect("Missing `DATABASE_URL` secret."),
+			report_channel_id: get("REPORT_CHANNEL_ID")
 				.expect("Missing `REPORT_CHANNEL_ID` secret.")
 				.parse()
 				.expect("`REPORT_CHANNEL_ID` must be a u64."),
-			guild_id: secret_store
-				.get("GUILD_ID")
-				.map(|guild_id| {
-					guild_id
-						.parse()
-						.expect("`GUILD_ID` must be a u64.")
-				}),
-			steam_token: secret_store
-				.get("STEAM_TOKEN")
-				.expect("Missing `STEAM_TOKEN` secret."),
-			owner_id: secret_store
-				.get("OWNER_ID")
+			guild_id: get("GUILD_ID").map(|guild_id| {
+				guild_id
+					.parse()
+					.expect("`GUILD_ID` must be a u64.")
+			}),
+			steam_token: get("STEAM_TOKEN").expect("Missing `STEAM_TOKEN` secret."),
+			owner_id: get("OWNER_ID")
 				.expect("Missing `OWNER_ID` secret.")
 				.parse()
 				.expect("`OWNER_ID` must be a u64."),
+			map_refresh_interval: interval(&get, "MAP_REFRESH_INTERVAL", 1800),
+			global_api_url: get("GLOBAL_API_URL")
+				.unwrap_or_else(|| String::from("https://kztimerglobal.com/api/v2.0")),
+			wr_feed_interval: interval(&get, "WR_FEED_INTERVAL", 60),
+			pb_notification_interval: interval(&get, "PB_NOTIFICATION_INTERVAL", 300),
+			health_check_interval: interval(&get, "HEALTH_CHECK_INTERVAL", 60),
+			progress_snapshot_interval: interval(&get, "PROGRESS_SNAPSHOT_INTERVAL", 86400),
+			competition_interval: interval(&get, "COMPETITION_INTERVAL", 300),
 		}
 	}
 }
+
+/// Reads an interval (in seconds) called `key`, or `default` if it isn't set.
+fn interval(get: impl Fn(&str) -> Option<String>, key: &str, default: u64) -> u64 {
+	let Some(interval) = get(key) else {
+		return default;
+	};
+
+	match interval.parse() {
+		Ok(0) => panic!("`{key}` must be at least 1."),
+		Ok(interval) => interval,
+		Err(_) => panic!("`{key}` must be a u64."),
+	}
+}
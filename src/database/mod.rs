@@ -0,0 +1,205 @@
+use {
+	crate::error::{Error, Result},
+	gokz_rs::{Mode, SteamID},
+	sqlx::{migrate::Migrator, FromRow, Pool, Postgres},
+	tracing::info,
+};
+
+mod users;
+#[cfg(test)]
+pub use users::InMemoryUserRepository;
+pub use users::{PgUserRepository, UserRepository};
+
+/// All migrations in `./migrations`, embedded into the binary.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Applies all migrations that have not been applied yet.
+///
+/// This will fail if the database has been migrated by a newer version of the bot, since we
+/// can't know whether the schema is still compatible in that case.
+pub async fn migrate(pool: &Pool<Postgres>) -> Result<()> {
+	let latest_known = MIGRATOR
+		.iter()
+		.map(|migration| migration.version)
+		.max()
+		.unwrap_or_default();
+
+	let (has_migrations_table,): (bool,) =
+		sqlx::query_as("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+			.fetch_one(pool)
+			.await?;
+
+	if has_migrations_table {
+		let (latest_applied,): (Option<i64>,) =
+			sqlx::query_as("SELECT MAX(version) FROM _sqlx_migrations WHERE success")
+				.fetch_one(pool)
+				.await?;
+
+		if let Some(latest_applied) = latest_applied {
+			if latest_applied > latest_known {
+				return Err(Error::SchemaTooNew {
+					found: latest_applied,
+					expected: latest_known,
+				});
+			}
+		}
+	}
+
+	MIGRATOR.run(pool).await?;
+
+	info!("Database schema is at version {latest_known}.");
+
+	Ok(())
+}
+
+#[derive(Debug, FromRow)]
+pub struct UserRow {
+	name: String,
+	discord_id: i64,
+	steam_id: Option<i64>,
+	mode: Option<i16>,
+	pb_notifications: bool,
+	notification_channel_id: Option<i64>,
+	last_record_id: Option<i64>,
+	verified: bool,
+	verification_code: Option<String>,
+	hide_unfiltered_maps: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+	pub name: String,
+	pub discord_id: u64,
+	pub steam_id: Option<SteamID>,
+	pub mode: Option<Mode>,
+
+	/// Whether the user wants to be notified about new personal bests
+	pub pb_notifications: bool,
+
+	/// Channel to send PB notifications to; DMs if this is `None`
+	pub notification_channel_id: Option<u64>,
+
+	/// The most recent record of this user that we already know about
+	pub last_record_id: Option<u32>,
+
+	/// Whether the user proved that `steam_id` belongs to them, see `/verify`
+	pub verified: bool,
+
+	/// The code the user has to put into their Steam name while a verification is pending
+	pub verification_code: Option<String>,
+
+	/// Whether map suggestions should leave out maps without a filter for `mode`
+	pub hide_unfiltered_maps: bool,
+}
+
+impl User {
+	/// A user without any preferences.
+	pub fn new(name: impl Into<String>, discord_id: u64) -> Self {
+		Self {
+			name: name.into(),
+			discord_id,
+			steam_id: None,
+			mode: None,
+			pb_notifications: false,
+			notification_channel_id: None,
+			last_record_id: None,
+			verified: false,
+			verification_code: None,
+			hide_unfiltered_maps: false,
+		}
+	}
+}
+
+impl TryFrom<UserRow> for User {
+	type Error = Error;
+
+	fn try_from(row: UserRow) -> Result<Self> {
+		let steam_id = row
+			.steam_id
+			.map(|id32| u32::try_from(id32).map(SteamID::from_id32))
+			.transpose()?;
+		let mode = match row.mode {
+			None => None,
+			Some(mode) => {
+				let mode_id = u8::try_from(mode)?;
+				Some(Mode::try_from(mode_id)?)
+			}
+		};
+
+		Ok(Self {
+			name: row.name,
+			discord_id: row.discord_id as u64,
+			steam_id,
+			mode,
+			pb_notifications: row.pb_notifications,
+			notification_channel_id: row
+				.notification_channel_id
+				.map(|id| id as u64),
+			last_record_id: row
+				.last_record_id
+				.map(u32::try_from)
+				.transpose()?,
+			verified: row.verified,
+			verification_code: row.verification_code,
+			hide_unfiltered_maps: row.hide_unfiltered_maps,
+		})
+	}
+}
+
+#[derive(Debug, FromRow)]
+pub struct GuildRow {
+	guild_id: i64,
+	mode: Option<i16>,
+	ephemeral: Option<bool>,
+	locale: Option<String>,
+	wr_feed_channel_id: Option<i64>,
+	pb_feed_channel_id: Option<i64>,
+}
+
+/// Per-server settings that can be changed with `/config`.
+#[derive(Debug, Clone)]
+pub struct Guild {
+	pub guild_id: u64,
+
+	/// Mode to fall back to if a user has no mode preference
+	pub mode: Option<Mode>,
+
+	/// Whether replies should be ephemeral, overriding each command's default
+	pub ephemeral: Option<bool>,
+
+	/// Locale used for formatting numbers
+	pub locale: Option<String>,
+
+	/// Channel that gets all new world records
+	pub wr_feed_channel_id: Option<u64>,
+
+	/// Channel that PB notifications get sent to
+	pub pb_feed_channel_id: Option<u64>,
+}
+
+impl TryFrom<GuildRow> for Guild {
+	type Error = Error;
+
+	fn try_from(row: GuildRow) -> Result<Self> {
+		let mode = match row.mode {
+			None => None,
+			Some(mode) => {
+				let mode_id = u8::try_from(mode)?;
+				Some(Mode::try_from(mode_id)?)
+			}
+		};
+
+		Ok(Self {
+			guild_id: row.guild_id as u64,
+			mode,
+			ephemeral: row.ephemeral,
+			locale: row.locale,
+			wr_feed_channel_id: row
+				.wr_feed_channel_id
+				.map(|id| id as u64),
+			pb_feed_channel_id: row
+				.pb_feed_channel_id
+				.map(|id| id as u64),
+		})
+	}
+}
@@ -0,0 +1,613 @@
+use {
+	super::{User, UserRow},
+	crate::{
+		error::Result,
+		players::{self, Pick},
+	},
+	gokz_rs::{Mode, SteamID},
+	poise::async_trait,
+	sqlx::{Pool, Postgres, QueryBuilder},
+};
+
+/// Storage for [`User`]s.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+	async fn get(&self, discord_id: u64) -> Result<Option<User>>;
+
+	/// The user whose name contains `name`. Returns `None` if several users match and none of
+	/// them is called exactly `name`, since we would just be guessing at that point.
+	async fn get_by_name(&self, name: &str) -> Result<Option<User>> {
+		Ok(match players::pick(self.search_by_name(name).await?, name) {
+			Pick::Player(user) => Some(user),
+			Pick::Nobody | Pick::Ambiguous(_) => None,
+		})
+	}
+
+	/// All users whose name contains `name`, ignoring case.
+	async fn search_by_name(&self, name: &str) -> Result<Vec<User>>;
+
+	/// Up to `limit` users with a SteamID set whose name contains `name`, ignoring case. Shorter
+	/// names come first, since they are closer to what was typed. Meant for autocompletion.
+	async fn suggest_linked(&self, name: &str, limit: usize) -> Result<Vec<User>>;
+
+	async fn get_by_steam_id(&self, steam_id: SteamID) -> Result<Option<User>>;
+	async fn get_by_mode(&self, mode: Mode) -> Result<Option<User>>;
+
+	/// All users who have a SteamID set.
+	async fn get_linked(&self) -> Result<Vec<User>>;
+
+	/// All users who have a verified SteamID set.
+	async fn get_verified(&self) -> Result<Vec<User>>;
+
+	/// All users with a verified SteamID who want to be notified about new personal bests.
+	async fn get_pb_subscribers(&self) -> Result<Vec<User>>;
+
+	/// Sets the mode preference of the user with the given `discord_id`, creating them if they
+	/// don't exist yet.
+	async fn set_mode(&self, name: &str, discord_id: u64, mode: Option<Mode>) -> Result<()>;
+
+	/// Sets the SteamID of the user with the given `discord_id`, creating them if they don't exist
+	/// yet. If the SteamID changes, the user's verification is reset, since it was for the old
+	/// account.
+	async fn set_steam_id(&self, name: &str, discord_id: u64, steam_id: SteamID) -> Result<()>;
+
+	/// Sets whether map suggestions should hide maps without a filter for the user's mode,
+	/// creating the user if they don't exist yet.
+	async fn set_hide_unfiltered_maps(&self, name: &str, discord_id: u64, hide: bool)
+		-> Result<()>;
+
+	/// Sets whether an existing user's SteamID is verified, and the code they have to put into
+	/// their Steam name while it isn't.
+	async fn set_verification(
+		&self,
+		discord_id: u64,
+		verified: bool,
+		verification_code: Option<&str>,
+	) -> Result<()>;
+
+	/// Turns PB notifications of an existing user on or off. `channel_id` is where they should be
+	/// sent; DMs if it is `None`.
+	async fn set_pb_notifications(
+		&self,
+		discord_id: u64,
+		enabled: bool,
+		channel_id: Option<u64>,
+	) -> Result<()>;
+
+	/// Remembers the most recent record of an existing user that we already know about.
+	async fn set_last_record_id(&self, discord_id: u64, last_record_id: Option<u32>) -> Result<()>;
+
+	/// Deletes a user. Returns `false` if there was no such user.
+	async fn delete(&self, discord_id: u64) -> Result<bool>;
+}
+
+/// [`UserRepository`] backed by the `users` table.
+#[derive(Debug, Clone)]
+pub struct PgUserRepository {
+	pool: Pool<Postgres>,
+}
+
+impl PgUserRepository {
+	pub fn new(pool: Pool<Postgres>) -> Self {
+		Self { pool }
+	}
+
+	/// `SELECT * FROM users WHERE ` — the caller is expected to push a condition.
+	fn select(&self) -> QueryBuilder<'static, Postgres> {
+		QueryBuilder::new("SELECT * FROM users WHERE ")
+	}
+
+	async fn fetch_optional(&self, mut query: QueryBuilder<'_, Postgres>) -> Result<Option<User>> {
+		query
+			.build_query_as::<UserRow>()
+			.fetch_optional(&self.pool)
+			.await?
+			.map(User::try_from)
+			.transpose()
+	}
+
+	async fn fetch_all(&self, mut query: QueryBuilder<'_, Postgres>) -> Result<Vec<User>> {
+		query
+			.build_query_as::<UserRow>()
+			.fetch_all(&self.pool)
+			.await?
+			.into_iter()
+			.map(User::try_from)
+			.collect()
+	}
+}
+
+#[async_trait]
+impl UserRepository for PgUserRepository {
+	async fn get(&self, discord_id: u64) -> Result<Option<User>> {
+		let mut query = self.select();
+		query
+			.push("discord_id = ")
+			.push_bind(discord_id as i64);
+
+		self.fetch_optional(query).await
+	}
+
+	async fn search_by_name(&self, name: &str) -> Result<Vec<User>> {
+		let mut query = self.select();
+		query
+			.push("name ILIKE ")
+			.push_bind(format!("%{name}%"));
+
+		self.fetch_all(query).await
+	}
+
+	async fn suggest_linked(&self, name: &str, limit: usize) -> Result<Vec<User>> {
+		let mut query = self.select();
+		query
+			.push("steam_id IS NOT NULL AND name ILIKE ")
+			.push_bind(format!("%{name}%"))
+			.push(" ORDER BY LENGTH(name), name LIMIT ")
+			.push_bind(limit as i64);
+
+		self.fetch_all(query).await
+	}
+
+	async fn get_by_steam_id(&self, steam_id: SteamID) -> Result<Option<User>> {
+		let mut query = self.select();
+		query
+			.push("steam_id = ")
+			.push_bind(steam_id.as_id32() as i64);
+
+		self.fetch_optional(query).await
+	}
+
+	async fn get_by_mode(&self, mode: Mode) -> Result<Option<User>> {
+		let mut query = self.select();
+		query
+			.push("mode = ")
+			.push_bind(mode as u8 as i16);
+
+		self.fetch_optional(query).await
+	}
+
+	async fn get_linked(&self) -> Result<Vec<User>> {
+		let mut query = self.select();
+		query.push("steam_id IS NOT NULL");
+
+		self.fetch_all(query).await
+	}
+
+	async fn get_verified(&self) -> Result<Vec<User>> {
+		let mut query = self.select();
+		query.push("verified AND steam_id IS NOT NULL");
+
+		self.fetch_all(query).await
+	}
+
+	async fn get_pb_subscribers(&self) -> Result<Vec<User>> {
+		let mut query = self.select();
+		query.push("pb_notifications AND verified AND steam_id IS NOT NULL");
+
+		self.fetch_all(query).await
+	}
+
+	async fn set_mode(&self, name: &str, discord_id: u64, mode: Option<Mode>) -> Result<()> {
+		sqlx::query(
+			r#"
+			INSERT INTO users (name, discord_id, mode)
+			VALUES ($1, $2, $3)
+			ON CONFLICT (discord_id) DO UPDATE SET mode = EXCLUDED.mode
+			"#,
+		)
+		.bind(name)
+		.bind(discord_id as i64)
+		.bind(mode.map(|mode| mode as u8 as i16))
+		.execute(&self.pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn set_steam_id(&self, name: &str, discord_id: u64, steam_id: SteamID) -> Result<()> {
+		sqlx::query(
+			r#"
+			INSERT INTO users (name, discord_id, steam_id)
+			VALUES ($1, $2, $3)
+			ON CONFLICT (discord_id) DO UPDATE SET
+			    steam_id = EXCLUDED.steam_id,
+			    verified = users.verified AND users.steam_id IS NOT DISTINCT FROM EXCLUDED.steam_id,
+			    verification_code = CASE
+			        WHEN users.steam_id IS NOT DISTINCT FROM EXCLUDED.steam_id
+			        THEN users.verification_code
+			    END
+			"#,
+		)
+		.bind(name)
+		.bind(discord_id as i64)
+		.bind(steam_id.as_id32() as i64)
+		.execute(&self.pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn set_hide_unfiltered_maps(
+		&self,
+		name: &str,
+		discord_id: u64,
+		hide: bool,
+	) -> Result<()> {
+		sqlx::query(
+			r#"
+			INSERT INTO users (name, discord_id, hide_unfiltered_maps)
+			VALUES ($1, $2, $3)
+			ON CONFLICT (discord_id) DO UPDATE SET hide_unfiltered_maps = EXCLUDED.hide_unfiltered_maps
+			"#,
+		)
+		.bind(name)
+		.bind(discord_id as i64)
+		.bind(hide)
+		.execute(&self.pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn set_verification(
+		&self,
+		discord_id: u64,
+		verified: bool,
+		verification_code: Option<&str>,
+	) -> Result<()> {
+		sqlx::query("UPDATE users SET verified = $2, verification_code = $3 WHERE discord_id = $1")
+			.bind(discord_id as i64)
+			.bind(verified)
+			.bind(verification_code)
+			.execute(&self.pool)
+			.await?;
+
+		Ok(())
+	}
+
+	async fn set_pb_notifications(
+		&self,
+		discord_id: u64,
+		enabled: bool,
+		channel_id: Option<u64>,
+	) -> Result<()> {
+		sqlx::query(
+			r#"
+			UPDATE users SET pb_notifications = $2, notification_channel_id = $3
+			WHERE discord_id = $1
+			"#,
+		)
+		.bind(discord_id as i64)
+		.bind(enabled)
+		.bind(channel_id.map(|id| id as i64))
+		.execute(&self.pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn set_last_record_id(&self, discord_id: u64, last_record_id: Option<u32>) -> Result<()> {
+		sqlx::query("UPDATE users SET last_record_id = $2 WHERE discord_id = $1")
+			.bind(discord_id as i64)
+			.bind(last_record_id.map(i64::from))
+			.execute(&self.pool)
+			.await?;
+
+		Ok(())
+	}
+
+	async fn delete(&self, discord_id: u64) -> Result<bool> {
+		let result = sqlx::query("DELETE FROM users WHERE discord_id = $1")
+			.bind(discord_id as i64)
+			.execute(&self.pool)
+			.await?;
+
+		Ok(result.rows_affected() > 0)
+	}
+}
+
+/// [`UserRepository`] that only lives in memory, so command logic can be tested without a
+/// database.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct InMemoryUserRepository {
+	users: std::sync::Mutex<std::collections::BTreeMap<u64, User>>,
+}
+
+#[cfg(test)]
+impl InMemoryUserRepository {
+	fn find(&self, predicate: impl Fn(&User) -> bool) -> Option<User> {
+		self.users
+			.lock()
+			.unwrap()
+			.values()
+			.find(|user| predicate(user))
+			.cloned()
+	}
+
+	fn filter(&self, predicate: impl Fn(&User) -> bool) -> Vec<User> {
+		self.users
+			.lock()
+			.unwrap()
+			.values()
+			.filter(|user| predicate(user))
+			.cloned()
+			.collect()
+	}
+
+	/// Applies `update` to the user with the given `discord_id`. If there is no such user, one
+	/// called `name` is created first, unless `name` is `None`.
+	fn update(&self, name: Option<&str>, discord_id: u64, update: impl FnOnce(&mut User)) {
+		let mut users = self.users.lock().unwrap();
+		let user = match name {
+			Some(name) => users
+				.entry(discord_id)
+				.or_insert_with(|| User::new(name, discord_id)),
+			None => match users.get_mut(&discord_id) {
+				Some(user) => user,
+				None => return,
+			},
+		};
+
+		update(user);
+	}
+
+	/// Creates `user`, or overwrites it if there is already a user with the same `discord_id`.
+	pub fn insert(&self, user: &User) {
+		self.users
+			.lock()
+			.unwrap()
+			.insert(user.discord_id, user.clone());
+	}
+}
+
+#[cfg(test)]
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+	async fn get(&self, discord_id: u64) -> Result<Option<User>> {
+		Ok(self.find(|user| user.discord_id == discord_id))
+	}
+
+	async fn search_by_name(&self, name: &str) -> Result<Vec<User>> {
+		let name = name.to_lowercase();
+
+		Ok(self.filter(|user| user.name.to_lowercase().contains(&name)))
+	}
+
+	async fn suggest_linked(&self, name: &str, limit: usize) -> Result<Vec<User>> {
+		let name = name.to_lowercase();
+		let mut users =
+			self.filter(|user| user.steam_id.is_some() && user.name.to_lowercase().contains(&name));
+
+		users.sort_by(|a, b| {
+			a.name
+				.len()
+				.cmp(&b.name.len())
+				.then_with(|| a.name.cmp(&b.name))
+		});
+		users.truncate(limit);
+
+		Ok(users)
+	}
+
+	async fn get_by_steam_id(&self, steam_id: SteamID) -> Result<Option<User>> {
+		Ok(self.find(|user| user.steam_id == Some(steam_id)))
+	}
+
+	async fn get_by_mode(&self, mode: Mode) -> Result<Option<User>> {
+		Ok(self.find(|user| user.mode == Some(mode)))
+	}
+
+	async fn get_linked(&self) -> Result<Vec<User>> {
+		Ok(self.filter(|user| user.steam_id.is_some()))
+	}
+
+	async fn get_verified(&self) -> Result<Vec<User>> {
+		Ok(self.filter(|user| user.verified && user.steam_id.is_some()))
+	}
+
+	async fn get_pb_subscribers(&self) -> Result<Vec<User>> {
+		Ok(self.filter(|user| user.pb_notifications && user.verified && user.steam_id.is_some()))
+	}
+
+	async fn set_mode(&self, name: &str, discord_id: u64, mode: Option<Mode>) -> Result<()> {
+		self.update(Some(name), discord_id, |user| user.mode = mode);
+
+		Ok(())
+	}
+
+	async fn set_steam_id(&self, name: &str, discord_id: u64, steam_id: SteamID) -> Result<()> {
+		self.update(Some(name), discord_id, |user| {
+			if user.steam_id != Some(steam_id) {
+				user.verified = false;
+				user.verification_code = None;
+			}
+
+			user.steam_id = Some(steam_id);
+		});
+
+		Ok(())
+	}
+
+	async fn set_hide_unfiltered_maps(
+		&self,
+		name: &str,
+		discord_id: u64,
+		hide: bool,
+	) -> Result<()> {
+		self.update(Some(name), discord_id, |user| user.hide_unfiltered_maps = hide);
+
+		Ok(())
+	}
+
+	async fn set_verification(
+		&self,
+		discord_id: u64,
+		verified: bool,
+		verification_code: Option<&str>,
+	) -> Result<()> {
+		self.update(None, discord_id, |user| {
+			user.verified = verified;
+			user.verification_code = verification_code.map(ToOwned::to_owned);
+		});
+
+		Ok(())
+	}
+
+	async fn set_pb_notifications(
+		&self,
+		discord_id: u64,
+		enabled: bool,
+		channel_id: Option<u64>,
+	) -> Result<()> {
+		self.update(None, discord_id, |user| {
+			user.pb_notifications = enabled;
+			user.notification_channel_id = channel_id;
+		});
+
+		Ok(())
+	}
+
+	async fn set_last_record_id(&self, discord_id: u64, last_record_id: Option<u32>) -> Result<()> {
+		self.update(None, discord_id, |user| user.last_record_id = last_record_id);
+
+		Ok(())
+	}
+
+	async fn delete(&self, discord_id: u64) -> Result<bool> {
+		Ok(self
+			.users
+			.lock()
+			.unwrap()
+			.remove(&discord_id)
+			.is_some())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn column_updates() {
+		let users = InMemoryUserRepository::default();
+		let steam_id = SteamID::from_id32(322356345);
+
+		users
+			.set_mode("AlphaKeks", 1, Some(Mode::SimpleKZ))
+			.await
+			.unwrap();
+		users
+			.set_steam_id("AlphaKeks", 1, steam_id)
+			.await
+			.unwrap();
+		users
+			.set_verification(1, true, None)
+			.await
+			.unwrap();
+		users
+			.set_last_record_id(1, Some(42))
+			.await
+			.unwrap();
+
+		// Setting the same SteamID again keeps the verification.
+		users
+			.set_steam_id("AlphaKeks", 1, steam_id)
+			.await
+			.unwrap();
+
+		let user = users.get(1).await.unwrap().unwrap();
+		assert_eq!(user.mode, Some(Mode::SimpleKZ));
+		assert_eq!(user.steam_id, Some(steam_id));
+		assert!(user.verified);
+		assert_eq!(user.last_record_id, Some(42));
+
+		// Only users that already exist get updated.
+		users
+			.set_last_record_id(2, Some(42))
+			.await
+			.unwrap();
+		assert_eq!(users.get(2).await, Ok(None));
+	}
+
+	#[tokio::test]
+	async fn ambiguous_names() {
+		let users = InMemoryUserRepository::default();
+		users.insert(&User::new("AlphaKeks", 1));
+
+		assert_eq!(
+			users
+				.get_by_name("alpha")
+				.await
+				.unwrap()
+				.map(|user| user.discord_id),
+			Some(1)
+		);
+
+		users.insert(&User::new("Alpha", 2));
+
+		// "Alpha" is an exact match, so we can still tell which one is meant.
+		assert_eq!(
+			users
+				.get_by_name("alpha")
+				.await
+				.unwrap()
+				.map(|user| user.discord_id),
+			Some(2)
+		);
+		assert_eq!(
+			users
+				.search_by_name("alpha")
+				.await
+				.unwrap()
+				.len(),
+			2
+		);
+
+		assert_eq!(users.get_by_name("lph").await.unwrap(), None);
+	}
+	#[tokio::test]
+	async fn suggestions() {
+		let users = InMemoryUserRepository::default();
+
+		for (discord_id, name) in [
+			(1, "AlphaKeks"),
+			(2, "alpha"),
+			(3, "Alphabet"),
+		] {
+			users
+				.set_steam_id(name, discord_id, SteamID::from_id32(discord_id as u32))
+				.await
+				.unwrap();
+		}
+
+		// Not linked, so there is nothing to suggest.
+		users.insert(&User::new("Alp", 4));
+
+		let names = |suggestions: Vec<User>| {
+			suggestions
+				.into_iter()
+				.map(|user| user.name)
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			names(
+				users
+					.suggest_linked("ALP", 25)
+					.await
+					.unwrap()
+			),
+			["alpha", "Alphabet", "AlphaKeks"]
+		);
+		assert_eq!(
+			names(
+				users
+					.suggest_linked("alp", 1)
+					.await
+					.unwrap()
+			),
+			["alpha"]
+		);
+	}
+}
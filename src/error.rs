@@ -1,5 +1,5 @@
 use {
-	crate::{state::State, target::Target},
+	crate::{api_cache::Upstream, state::State, target::Target},
 	poise::FrameworkError,
 	std::num::TryFromIntError,
 	thiserror::Error,
@@ -29,6 +29,9 @@ pub enum Error {
 	#[error("{error}")]
 	GOKZ { error: gokz_rs::Error },
 
+	#[error("The {api} is currently unavailable. Please try again later.")]
+	ApiUnavailable { api: Upstream },
+
 	#[error("User `{user}` is not in the database.")]
 	UserNotInDatabase { user: Target },
 
@@ -38,20 +41,68 @@ pub enum Error {
 	#[error("`{input}` is not a global map.")]
 	MapNotGlobal { input: String },
 
+	#[error("`{map}` doesn't have a bonus {course}. Valid courses: {valid}")]
+	InvalidCourse { map: String, course: u8, valid: String },
+
 	#[error("User does not have a mode preference set. Please use `/mode` to save a mode preference or specify one.")]
 	NoModePreference,
 
+	#[error("User does not have a SteamID set. Please use `/setsteam` to save a SteamID.")]
+	NoSteamID,
+
+	#[error("User has not verified their SteamID. Please use `/verify` to prove that it belongs to you.")]
+	NotVerified,
+
+	#[error("Couldn't find a player for `{input}`.")]
+	PlayerNotFound { input: String },
+
+	#[error("No player was chosen.")]
+	NoPlayerChosen,
+
 	#[error("No records found.")]
 	NoRecords,
 
+	#[error("Not enough data yet. Stats of players with a linked SteamID are saved regularly, so please check back later.")]
+	NotEnoughProgressData,
+
 	#[error("No database entries found.")]
 	NoDatabaseEntries,
 
+	#[error("Nobody on this server has a verified SteamID. Use `/setsteam` and `/verify` to link yours!")]
+	NoVerifiedMembers,
+
+	#[error("There is no competition on this server right now. Admins can create one with `/competition create`.")]
+	NoActiveCompetition,
+
+	#[error("There already is a competition on this server. Use `/competition end` or `/competition cancel` first.")]
+	CompetitionAlreadyRunning,
+
+	#[error("The competition's channel was deleted or the bot can't post in it anymore.")]
+	CompetitionChannelUnavailable,
+
+	#[error("`{input}` is not a valid date. Please use the format {expected}.")]
+	InvalidDate { input: String, expected: &'static str },
+
 	#[error("Failed to access database.")]
 	DatabaseAccess,
+
+	#[error("Failed to migrate database.")]
+	Migration,
+
+	#[error("Database schema version `{found}` is newer than the latest version this bot knows about (`{expected}`).")]
+	SchemaTooNew { found: i64, expected: i64 },
 }
 
 impl Error {
+	/// Used when none of the requests for a command returned anything. If that was because the API
+	/// is down, we want to tell the user about that instead.
+	pub fn or_no_records(self) -> Self {
+		match self {
+			Self::ApiUnavailable { .. } => self,
+			_ => Self::NoRecords,
+		}
+	}
+
 	#[tracing::instrument(skip(error))]
 	pub async fn handle(error: FrameworkError<'_, State, crate::error::Error>) {
 		warn!("Slash Command failed.");
@@ -104,12 +155,36 @@ impl Error {
 			}
 		};
 
+		let unavailable = match &error {
+			poise::FrameworkError::Command { error: Self::ApiUnavailable { api }, .. } => {
+				Some(*api)
+			}
+			_ => None,
+		};
+
 		if let Some(ctx) = &error.ctx() {
 			if let Err(why) = ctx
 				.send(|reply| {
-					reply
-						.ephemeral(ephemeral)
-						.content(&content)
+					reply.ephemeral(ephemeral);
+
+					match unavailable {
+						None => reply.content(&content),
+						Some(api) => reply.embed(|embed| {
+							embed
+								.color((243, 139, 168))
+								.title(format!("{api} is currently unavailable"))
+								.description(format!(
+									"Too many requests to the {api} failed recently, so the bot \
+									 stopped asking for a bit. Please try again later or check \
+									 `/apistatus`."
+								))
+								.footer(|footer| {
+									footer
+										.text(&ctx.data().schnose)
+										.icon_url(&ctx.data().icon_url)
+								})
+						}),
+					}
 				})
 				.await
 			{
@@ -159,6 +234,30 @@ impl From<gokz_rs::Error> for Error {
 	}
 }
 
+impl From<reqwest::Error> for Error {
+	fn from(error: reqwest::Error) -> Self {
+		error!("HTTP Error.");
+		debug!("{error:?}");
+		Self::Custom(String::from("Failed to make HTTP request."))
+	}
+}
+
+impl From<image::ImageError> for Error {
+	fn from(error: image::ImageError) -> Self {
+		error!("Image Error.");
+		debug!("{error:?}");
+		Self::Custom(String::from("Failed to render image."))
+	}
+}
+
+impl From<sqlx::migrate::MigrateError> for Error {
+	fn from(error: sqlx::migrate::MigrateError) -> Self {
+		error!("Migration Error.");
+		debug!("{error:?}");
+		Self::Migration
+	}
+}
+
 #[allow(clippy::cognitive_complexity)] // ???
 impl From<sqlx::Error> for Error {
 	fn from(error: sqlx::Error) -> Self {
@@ -0,0 +1,135 @@
+use {
+	crate::error::Result,
+	schnosebot::global_map::GlobalMap,
+	std::{
+		collections::HashMap,
+		sync::{Arc, RwLock},
+		time::Duration,
+	},
+	tracing::{error, info, trace},
+};
+
+/// Shared handle to the cache of all global maps.
+///
+/// Cloning this is cheap and every clone refers to the same cache. Readers get a snapshot of the
+/// map pool that stays valid even if the cache gets refreshed while they are still using it.
+#[derive(Debug, Clone)]
+pub struct MapCache {
+	inner: Arc<RwLock<MapPool>>,
+}
+
+#[derive(Debug, Clone)]
+struct MapPool {
+	maps: Arc<Vec<GlobalMap>>,
+	names: Arc<Vec<String>>,
+}
+
+impl MapPool {
+	fn new(maps: Vec<GlobalMap>) -> Self {
+		let names = maps
+			.iter()
+			.map(|map| map.name.clone())
+			.collect();
+
+		Self {
+			maps: Arc::new(maps),
+			names: Arc::new(names),
+		}
+	}
+}
+
+impl MapCache {
+	/// Fetches the global map pool for the first time.
+	pub async fn new(gokz_client: &gokz_rs::Client) -> Result<Self> {
+		let maps = GlobalMap::fetch(true, gokz_client).await?;
+
+		Ok(Self {
+			inner: Arc::new(RwLock::new(MapPool::new(maps))),
+		})
+	}
+
+	/// Snapshot of all global maps.
+	pub fn maps(&self) -> Arc<Vec<GlobalMap>> {
+		Arc::clone(&self.read().maps)
+	}
+
+	/// Snapshot of all global map names.
+	pub fn names(&self) -> Arc<Vec<String>> {
+		Arc::clone(&self.read().names)
+	}
+
+	fn read(&self) -> MapPool {
+		// A poisoned lock only means that a writer panicked while swapping the pool. The old pool
+		// is still intact in that case, so we just keep using it.
+		self.inner
+			.read()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+			.clone()
+	}
+
+	/// Fetches the global map pool again and swaps it in if that succeeded. If it failed, the
+	/// previous pool will be kept.
+	pub async fn refresh(&self, gokz_client: &gokz_rs::Client) -> Result<()> {
+		let new_maps = GlobalMap::fetch(true, gokz_client).await?;
+
+		log_changes(&self.maps(), &new_maps);
+
+		*self
+			.inner
+			.write()
+			.unwrap_or_else(|poisoned| poisoned.into_inner()) = MapPool::new(new_maps);
+
+		Ok(())
+	}
+
+	/// Spawns a background task that calls [`MapCache::refresh`] every `interval`.
+	pub fn spawn_refresh_task(&self, gokz_client: gokz_rs::Client, interval: Duration) {
+		let cache = self.clone();
+
+		tokio::spawn(async move {
+			let mut interval = tokio::time::interval(interval);
+
+			// The first tick completes immediately, but we just fetched the maps on startup.
+			interval.tick().await;
+
+			loop {
+				interval.tick().await;
+
+				trace!("Refreshing global maps.");
+
+				if let Err(why) = cache.refresh(&gokz_client).await {
+					error!("Failed to refresh global maps: {why:?}");
+				}
+			}
+		});
+	}
+}
+
+/// Logs which maps got added, removed or re-tiered between two versions of the map pool.
+fn log_changes(old_maps: &[GlobalMap], new_maps: &[GlobalMap]) {
+	let old_tiers = old_maps
+		.iter()
+		.map(|map| (map.id, (map.name.as_str(), map.tier as u8)))
+		.collect::<HashMap<_, _>>();
+
+	let new_tiers = new_maps
+		.iter()
+		.map(|map| (map.id, (map.name.as_str(), map.tier as u8)))
+		.collect::<HashMap<_, _>>();
+
+	for (id, (name, tier)) in &new_tiers {
+		match old_tiers.get(id) {
+			None => info!("New global map: `{name}` (T{tier})"),
+			Some((_, old_tier)) if old_tier != tier => {
+				info!("`{name}` got re-tiered: T{old_tier} -> T{tier}")
+			}
+			Some(_) => {}
+		}
+	}
+
+	for (id, (name, tier)) in &old_tiers {
+		if !new_tiers.contains_key(id) {
+			info!("`{name}` (T{tier}) is no longer global.");
+		}
+	}
+}
@@ -0,0 +1,115 @@
+//! Per-server settings from `/config`, kept in memory so commands can respect them without asking
+//! the database first. Discord only gives us 3 seconds to respond to an interaction, and most
+//! commands need to know whether to respond ephemerally before they can do anything else.
+//!
+//! `/config` is the only thing that changes these settings, so the cache is loaded once on
+//! startup and updated whenever `/config` writes to the database.
+
+use {
+	crate::{
+		database::{Guild, GuildRow},
+		error::Result,
+	},
+	sqlx::{Pool, Postgres},
+	std::{
+		collections::HashMap,
+		sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+	},
+	tracing::error,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct GuildSettings {
+	guilds: Arc<RwLock<HashMap<u64, Guild>>>,
+}
+
+impl GuildSettings {
+	/// Loads the settings of every server from the database.
+	pub async fn load(db: &Pool<Postgres>) -> Result<Self> {
+		let settings = Self::default();
+
+		for row in sqlx::query_as::<_, GuildRow>("SELECT * FROM guilds")
+			.fetch_all(db)
+			.await?
+		{
+			match Guild::try_from(row) {
+				Ok(guild) => settings.set(guild),
+				Err(why) => error!("Failed to parse guild: {why:?}"),
+			}
+		}
+
+		Ok(settings)
+	}
+
+	/// The settings of the server with the given `guild_id`, if it has any.
+	pub fn get(&self, guild_id: u64) -> Option<Guild> {
+		self.read().get(&guild_id).cloned()
+	}
+
+	/// Replaces the settings of `guild`'s server.
+	pub fn set(&self, guild: Guild) {
+		self.write()
+			.insert(guild.guild_id, guild);
+	}
+
+	/// Forgets the settings of the server with the given `guild_id`.
+	pub fn remove(&self, guild_id: u64) {
+		self.write().remove(&guild_id);
+	}
+
+	fn read(&self) -> RwLockReadGuard<'_, HashMap<u64, Guild>> {
+		// Every write replaces a whole entry, so there is nothing half-updated to worry about.
+		self.guilds
+			.read()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn write(&self) -> RwLockWriteGuard<'_, HashMap<u64, Guild>> {
+		self.guilds
+			.write()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn guild(guild_id: u64, ephemeral: Option<bool>) -> Guild {
+		Guild {
+			guild_id,
+			mode: None,
+			ephemeral,
+			locale: None,
+			wr_feed_channel_id: None,
+			pb_feed_channel_id: None,
+		}
+	}
+
+	#[test]
+	fn set_and_remove() {
+		let settings = GuildSettings::default();
+		assert!(settings.get(1).is_none());
+
+		settings.set(guild(1, Some(true)));
+		settings.set(guild(2, None));
+		assert_eq!(
+			settings
+				.get(1)
+				.and_then(|guild| guild.ephemeral),
+			Some(true)
+		);
+
+		settings.set(guild(1, Some(false)));
+		assert_eq!(
+			settings
+				.get(1)
+				.and_then(|guild| guild.ephemeral),
+			Some(false)
+		);
+
+		settings.remove(1);
+		assert!(settings.get(1).is_none());
+		assert!(settings.get(2).is_some());
+	}
+}
@@ -18,35 +18,62 @@
 #![deny(clippy::perf, clippy::correctness)]
 
 use {
-	crate::{
-		config::Config,
-		shuttle_integration::{SchnoseBot, ShuttleResult},
-		state::State,
-	},
+	crate::{error::Error, state::State},
 	poise::{
 		serenity_prelude::{GatewayIntents, GuildId},
-		Command, Framework, FrameworkOptions, PrefixFrameworkOptions,
+		Command, Framework, FrameworkBuilder, FrameworkOptions, PrefixFrameworkOptions,
 	},
-	shuttle_secrets::SecretStore,
-	std::collections::HashSet,
+	std::{collections::HashSet, sync::Arc},
 	tracing::info,
 };
 
+mod api_cache;
+mod circuit_breaker;
 mod commands;
+mod competitions;
 mod config;
 mod database;
 mod error;
 mod event_handler;
+mod global_maps;
+mod guild_settings;
+mod pb_notifications;
+mod players;
+mod progress;
+mod recent_maps;
+mod recent_players;
+mod render;
+#[cfg(feature = "shuttle")]
 mod shuttle_integration;
+#[cfg(not(feature = "shuttle"))]
+mod standalone;
 mod state;
+mod stats;
+mod steam;
 mod target;
 mod utils;
+mod wr_feed;
 
+#[cfg(feature = "shuttle")]
 #[shuttle_runtime::main]
-async fn schnosebot(#[shuttle_secrets::Secrets] secret_store: SecretStore) -> ShuttleResult {
-	let config = Config::new(&secret_store);
+async fn schnosebot(
+	#[shuttle_secrets::Secrets] secret_store: shuttle_secrets::SecretStore,
+) -> shuttle_integration::ShuttleResult {
+	let config = config::Config::new(&secret_store);
 	let state = State::new(config).await;
 
+	Ok(shuttle_integration::SchnoseBot::new(framework(state)))
+}
+
+#[cfg(not(feature = "shuttle"))]
+#[tokio::main]
+async fn main() {
+	standalone::run().await;
+}
+
+/// Sets up the bot with all of its commands and background tasks. This is shared between
+/// shuttle.rs and the standalone binary.
+fn framework(state: State) -> FrameworkBuilder<State, Error> {
 	let framework_options = FrameworkOptions {
 		owners: HashSet::from_iter([state.config.owner_id.into()]),
 		prefix_options: PrefixFrameworkOptions { ignore_bots: true, ..Default::default() },
@@ -56,23 +83,33 @@ async fn schnosebot(#[shuttle_secrets::Secrets] secret_store: SecretStore) -> Sh
 			commands::bpb(),
 			commands::btop(),
 			commands::bwr(),
+			commands::compare(),
+			commands::competition(),
+			commands::config(),
 			commands::db(),
 			commands::help(),
 			commands::invite(),
 			commands::map(),
+			commands::maps(),
 			commands::maptop(),
 			commands::mode(),
 			commands::nocrouch(),
+			commands::notifications(),
 			commands::pb(),
 			commands::ping(),
 			commands::profile(),
+			commands::progress(),
 			commands::random(),
 			commands::recent(),
 			commands::report(),
+			commands::serverlb(),
+			commands::servertop(),
 			commands::setsteam(),
 			commands::top(),
 			commands::unfinished(),
+			commands::verify(),
 			commands::wr(),
+			commands::wrfeed(),
 		],
 		event_handler: |ctx, event, framework_ctx, state| {
 			Box::pin(event_handler::handle(ctx, event, framework_ctx, state))
@@ -87,39 +124,42 @@ async fn schnosebot(#[shuttle_secrets::Secrets] secret_store: SecretStore) -> Sh
 		| GatewayIntents::GUILD_MESSAGES
 		| GatewayIntents::MESSAGE_CONTENT;
 
-	Ok(SchnoseBot::new(
-		Framework::builder()
-			.options(framework_options)
-			.token(token)
-			.intents(intents)
-			.setup(move |ctx, _, framework| {
-				Box::pin(async move {
-					let commands = &framework.options().commands;
+	Framework::builder()
+		.options(framework_options)
+		.token(token)
+		.intents(intents)
+		.setup(move |ctx, _, framework| {
+			Box::pin(async move {
+				wr_feed::spawn(Arc::clone(&ctx.http), state.clone());
+				pb_notifications::spawn(Arc::clone(&ctx.http), state.clone());
+				progress::spawn(state.clone());
+				competitions::spawn(Arc::clone(&ctx.http), state.clone());
 
-					match state.config.guild_id {
-						Some(guild_id) => {
-							let guild_id = GuildId(guild_id);
-							poise::builtins::register_in_guild(ctx, commands, guild_id)
-								.await
-								.expect("Failed to register commands for GuildID `{guild_id}`.");
-						}
-						None => {
-							poise::builtins::register_globally(ctx, commands)
-								.await
-								.expect("Failed to register commands globally.");
-						}
-					}
+				let commands = &framework.options().commands;
 
-					for Command { name, .. } in commands {
-						info!(
-							"[{}] Successfully registered command `{}`.",
-							if state.config.guild_id.is_some() { "DEV" } else { "PROD" },
-							name,
-						);
+				match state.config.guild_id {
+					Some(guild_id) => {
+						let guild_id = GuildId(guild_id);
+						poise::builtins::register_in_guild(ctx, commands, guild_id)
+							.await
+							.expect("Failed to register commands for GuildID `{guild_id}`.");
 					}
+					None => {
+						poise::builtins::register_globally(ctx, commands)
+							.await
+							.expect("Failed to register commands globally.");
+					}
+				}
+
+				for Command { name, .. } in commands {
+					info!(
+						"[{}] Successfully registered command `{}`.",
+						if state.config.guild_id.is_some() { "DEV" } else { "PROD" },
+						name,
+					);
+				}
 
-					Ok(state)
-				})
-			}),
-	))
+				Ok(state)
+			})
+		})
 }
@@ -0,0 +1,284 @@
+//! Background task that notifies users about their new personal bests.
+//!
+//! Users can opt in to this with `/notifications`. When they do, we remember all of their current
+//! personal bests so we know the old time once they improve. After that, we regularly check their
+//! most recent records and compare them against what we know.
+
+use {
+	crate::{
+		circuit_breaker::UpstreamError,
+		database::{self, UserRepository},
+		error::Result,
+		state::State,
+	},
+	gokz_rs::{global_api, schnose_api, Mode, SteamID},
+	poise::serenity_prelude::{ChannelId, CreateEmbed, Http, UserId},
+	schnosebot::time,
+	sqlx::{Pool, Postgres, QueryBuilder},
+	std::{sync::Arc, time::Duration},
+	tracing::{error, info, trace},
+};
+
+/// Saves a player's current personal bests so future records can be compared against them.
+pub async fn snapshot_pbs(
+	discord_id: u64,
+	steam_id: SteamID,
+	gokz_client: &gokz_rs::Client,
+	db: &Pool<Postgres>,
+) -> Result<()> {
+	let mut snapshot = Vec::new();
+
+	for mode in [
+		Mode::KZTimer,
+		Mode::SimpleKZ,
+		Mode::Vanilla,
+	] {
+		for runtype in [true, false] {
+			match global_api::get_player_records(
+				steam_id.into(),
+				mode,
+				runtype,
+				0,
+				9999,
+				gokz_client,
+			)
+			.await
+			{
+				Ok(records) if !records.is_empty() => snapshot.push((mode, runtype, records)),
+				// If the API is down, an empty snapshot would turn every future record into a
+				// "first completion", so we'd rather fail and let the user try again later.
+				Err(why) if why.is_upstream_failure() => return Err(why.into()),
+				_ => continue,
+			};
+		}
+	}
+
+	let mut transaction = db.begin().await?;
+
+	// The user might have changed their SteamID since the last snapshot, and we don't want to
+	// compare their records against somebody else's.
+	sqlx::query("DELETE FROM personal_bests WHERE discord_id = $1")
+		.bind(discord_id as i64)
+		.execute(&mut transaction)
+		.await?;
+
+	for (mode, runtype, records) in snapshot {
+		let mut query = QueryBuilder::new(
+			"INSERT INTO personal_bests (discord_id, map_name, stage, mode, runtype, time) ",
+		);
+
+		query
+			.push_values(records, |mut query, record| {
+				query
+					.push_bind(discord_id as i64)
+					.push_bind(record.map_name)
+					.push_bind(record.stage as i16)
+					.push_bind(mode as u8 as i16)
+					.push_bind(runtype)
+					.push_bind(record.time);
+			})
+			.push(" ON CONFLICT (discord_id, map_name, stage, mode, runtype) DO UPDATE SET time = EXCLUDED.time");
+
+		query
+			.build()
+			.execute(&mut transaction)
+			.await?;
+	}
+
+	transaction.commit().await?;
+
+	Ok(())
+}
+
+/// Spawns the background task that checks opted in users for new personal bests.
+pub fn spawn(http: Arc<Http>, state: State) {
+	tokio::spawn(async move {
+		let mut interval =
+			tokio::time::interval(Duration::from_secs(state.config.pb_notification_interval));
+
+		loop {
+			interval.tick().await;
+
+			trace!("Checking for new personal bests.");
+
+			let users = match state.users.get_pb_subscribers().await {
+				Ok(users) => users,
+				Err(why) => {
+					error!("Failed to fetch users for PB notifications: {why:?}");
+					continue;
+				}
+			};
+
+			for user in users {
+				if let Err(why) = check_user(&http, &state, &user).await {
+					error!("Failed to check `{}` for new personal bests: {why:?}", user.discord_id);
+				}
+			}
+		}
+	});
+}
+
+async fn check_user(http: &Http, state: &State, user: &database::User) -> Result<()> {
+	let Some(steam_id) = user.steam_id else {
+		return Ok(());
+	};
+
+	let last_record_id = user.last_record_id.unwrap_or_default();
+
+	let mut records = schnose_api::get_recent(steam_id.into(), 10, &state.gokz_client)
+		.await?
+		.into_iter()
+		.filter(|record| record.id > last_record_id)
+		.collect::<Vec<_>>();
+
+	if records.is_empty() {
+		return Ok(());
+	}
+
+	// Oldest records first, in case someone improved on the same map multiple times.
+	records.sort_unstable_by_key(|record| record.id);
+
+	for record in &records {
+		check_record(http, state, user, record).await?;
+
+		// Remember every record right away, so a failure later on doesn't make us notify about
+		// the same records again.
+		state
+			.users
+			.set_last_record_id(user.discord_id, Some(record.id))
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Notifies `user` about `record` if it's a new personal best.
+async fn check_record(
+	http: &Http,
+	state: &State,
+	user: &database::User,
+	record: &schnose_api::Record,
+) -> Result<()> {
+	let db = &state.database_connection;
+
+	let runtype = record.teleports > 0;
+	let stage = record.course.stage;
+
+	let old_time: Option<(f64,)> = sqlx::query_as(
+		r#"
+		SELECT time FROM personal_bests
+		WHERE discord_id = $1 AND map_name = $2 AND stage = $3 AND mode = $4 AND runtype = $5
+		"#,
+	)
+	.bind(user.discord_id as i64)
+	.bind(&record.map_name)
+	.bind(stage as i16)
+	.bind(record.mode as u8 as i16)
+	.bind(runtype)
+	.fetch_optional(db)
+	.await?;
+
+	let old_time = old_time.map(|(time,)| time);
+
+	if old_time.map_or(false, |old_time| old_time <= record.time) {
+		return Ok(());
+	}
+
+	sqlx::query(
+		r#"
+		INSERT INTO personal_bests (discord_id, map_name, stage, mode, runtype, time)
+		VALUES ($1, $2, $3, $4, $5, $6)
+		ON CONFLICT (discord_id, map_name, stage, mode, runtype) DO UPDATE SET time = EXCLUDED.time
+		"#,
+	)
+	.bind(user.discord_id as i64)
+	.bind(&record.map_name)
+	.bind(stage as i16)
+	.bind(record.mode as u8 as i16)
+	.bind(runtype)
+	.bind(record.time)
+	.execute(db)
+	.await?;
+
+	info!("New PB for `{}` on `{}`.", user.discord_id, record.map_name);
+
+	let place = global_api::get_place(record.id, &state.gokz_client)
+		.await
+		.map(|place| format!("#{place}"))
+		.unwrap_or_else(|_| String::from("?"));
+
+	let (map_url, map_thumbnail) = state
+		.global_maps
+		.maps()
+		.iter()
+		.find(|map| map.name == record.map_name)
+		.map(|map| {
+			(
+				format!("{}?{}=", map.kzgo_link(), record.mode.short().to_lowercase()),
+				map.thumbnail(),
+			)
+		})
+		.unwrap_or_else(|| (String::new(), String::from("https://kzgo.eu/kz_default.png")));
+
+	let (old_time, improvement) = match old_time {
+		None => (String::from("😔"), String::from("First completion!")),
+		Some(old_time) => {
+			(time::format(old_time), format!("-{}", time::format(old_time - record.time)))
+		}
+	};
+
+	let mut embed = CreateEmbed::default();
+	embed
+		.color(state.color)
+		.title(format!(
+			"[New PB] {} on {}{}",
+			&record.player.name,
+			&record.map_name,
+			if stage == 0 { String::new() } else { format!(" B{stage}") }
+		))
+		.url(map_url)
+		.thumbnail(map_thumbnail)
+		.field("Old Time", old_time, true)
+		.field("New Time", time::format(record.time), true)
+		.field("Improvement", improvement, true)
+		.field("Place", place, true)
+		.footer(|footer| {
+			footer
+				.text(format!(
+					"{} | Mode: {} {}",
+					state.schnose,
+					record.mode.short(),
+					if runtype { "TP" } else { "PRO" }
+				))
+				.icon_url(&state.icon_url)
+		});
+
+	let sent = match user.notification_channel_id {
+		Some(channel_id) => {
+			ChannelId(channel_id)
+				.send_message(http, |message| {
+					message
+						.content(format!("<@{}>", user.discord_id))
+						.set_embed(embed)
+				})
+				.await
+		}
+		None => match UserId(user.discord_id)
+			.create_dm_channel(http)
+			.await
+		{
+			Ok(channel) => {
+				channel
+					.send_message(http, |message| message.set_embed(embed))
+					.await
+			}
+			Err(why) => Err(why),
+		},
+	};
+
+	if let Err(why) = sent {
+		error!("Failed to send PB notification to `{}`: {why:?}", user.discord_id);
+	}
+
+	Ok(())
+}
@@ -0,0 +1,353 @@
+//! Turning a player's name into a [`SteamID`].
+//!
+//! Names are not unique, and both the GlobalAPI and our database match them partially. Instead of
+//! silently picking whichever player comes first, we collect every candidate and let the user
+//! choose one from a select menu if the name is ambiguous.
+
+use {
+	crate::{
+		api_cache::{Endpoint, MAX_CONCURRENT_REQUESTS},
+		database::{User, UserRepository},
+		error::{Error, Result},
+		state::{Context, StateContainer},
+	},
+	chrono::{DateTime, NaiveDateTime, TimeZone, Utc},
+	futures::StreamExt,
+	gokz_rs::{PlayerIdentifier, SteamID},
+	poise::serenity_prelude::{CollectComponentInteraction, InteractionResponseType},
+	serde::Deserialize,
+	std::time::Duration,
+	tracing::warn,
+};
+
+/// The most players we show in the select menu.
+const MAX_CANDIDATES: usize = 10;
+
+/// How long the user has to pick a player.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A player who might be the one the user meant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+	pub name: String,
+	pub steam_id: SteamID,
+
+	/// When the player last set a personal best
+	pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Anything that can be picked by name with [`pick`].
+pub trait Named {
+	fn name(&self) -> &str;
+}
+
+impl Named for Candidate {
+	fn name(&self) -> &str {
+		&self.name
+	}
+}
+
+impl Named for User {
+	fn name(&self) -> &str {
+		&self.name
+	}
+}
+
+/// The outcome of [`pick`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pick<T> {
+	/// Nobody is called like that.
+	Nobody,
+
+	/// There is only one player it could be.
+	Player(T),
+
+	/// The user has to choose.
+	Ambiguous(Vec<T>),
+}
+
+/// A player as returned by the GlobalAPI's `/players` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+struct ApiPlayer {
+	name: String,
+	steamid64: String,
+}
+
+/// A record as returned by the GlobalAPI's `/records/top/recent` endpoint. We only care about
+/// when it was set.
+#[derive(Debug, Clone, Deserialize)]
+struct ApiRecord {
+	created_on: String,
+}
+
+/// Resolves `name` to a player, asking the user if there are several players it could be.
+pub async fn resolve_name(ctx: &Context<'_>, name: String) -> Result<PlayerIdentifier> {
+	let candidates = match search(ctx, &name).await {
+		Ok(candidates) => candidates,
+		Err(why) => {
+			// The GlobalAPI will still find _someone_ with that name.
+			warn!("Failed to search for players called `{name}`: {why:?}");
+			return Ok(name.into());
+		}
+	};
+
+	match pick(candidates, &name) {
+		Pick::Nobody => Ok(name.into()),
+		Pick::Player(candidate) => Ok(candidate.steam_id.into()),
+		Pick::Ambiguous(mut candidates) => {
+			let activity = futures::stream::iter(&candidates)
+				.map(|candidate| last_seen(ctx, candidate.steam_id))
+				.buffered(MAX_CONCURRENT_REQUESTS)
+				.collect::<Vec<_>>()
+				.await;
+
+			for (candidate, last_seen) in candidates.iter_mut().zip(activity) {
+				candidate.last_seen = last_seen.unwrap_or_default();
+			}
+
+			// Most recently active players are the most likely ones, so they should make the cut.
+			candidates.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+			candidates.truncate(MAX_CANDIDATES);
+
+			choose(ctx, &name, &candidates)
+				.await
+				.map(PlayerIdentifier::from)
+		}
+	}
+}
+
+/// Decides whether `name` clearly refers to one of the `candidates`. A player called exactly
+/// `name` (ignoring case) always wins.
+pub fn pick<T: Named>(mut candidates: Vec<T>, name: &str) -> Pick<T> {
+	let exact_matches = candidates
+		.iter()
+		.enumerate()
+		.filter(|(_, candidate)| {
+			candidate
+				.name()
+				.eq_ignore_ascii_case(name)
+		})
+		.map(|(idx, _)| idx)
+		.collect::<Vec<_>>();
+
+	if let [idx] = exact_matches[..] {
+		return Pick::Player(candidates.swap_remove(idx));
+	}
+
+	match candidates.len() {
+		0 => Pick::Nobody,
+		1 => Pick::Player(candidates.remove(0)),
+		_ => Pick::Ambiguous(candidates),
+	}
+}
+
+/// Every player in the database and the GlobalAPI whose name contains `name`, without duplicates.
+async fn search(ctx: &Context<'_>, name: &str) -> Result<Vec<Candidate>> {
+	let mut candidates = ctx
+		.users()
+		.search_by_name(name)
+		.await?
+		.into_iter()
+		.filter_map(|user| {
+			Some(Candidate {
+				steam_id: user.steam_id?,
+				name: user.name,
+				last_seen: None,
+			})
+		})
+		.collect::<Vec<_>>();
+
+	let players = ctx
+		.api_cache()
+		.get_global_api::<Vec<ApiPlayer>>(
+			Endpoint::PlayerSearch,
+			&ctx.config().global_api_url,
+			"/players",
+			&[("name", name), ("limit", "25")],
+			ctx.gokz_client(),
+		)
+		.await?;
+
+	for player in players {
+		let Ok(steam_id) = SteamID::new(&player.steamid64) else {
+			continue;
+		};
+
+		if candidates
+			.iter()
+			.all(|candidate| candidate.steam_id != steam_id)
+		{
+			candidates.push(Candidate {
+				name: player.name,
+				steam_id,
+				last_seen: None,
+			});
+		}
+	}
+
+	Ok(candidates)
+}
+
+/// When `steam_id` last set a personal best.
+async fn last_seen(ctx: &Context<'_>, steam_id: SteamID) -> Result<Option<DateTime<Utc>>> {
+	let steam_id = steam_id.as_id64().to_string();
+
+	let records = ctx
+		.api_cache()
+		.get_global_api::<Vec<ApiRecord>>(
+			Endpoint::LastSeen,
+			&ctx.config().global_api_url,
+			"/records/top/recent",
+			&[
+				("steamid64", steam_id.as_str()),
+				("tickrate", "128"),
+				("limit", "1"),
+			],
+			ctx.gokz_client(),
+		)
+		.await?;
+
+	Ok(records
+		.into_iter()
+		.next()
+		.and_then(|record| {
+			NaiveDateTime::parse_from_str(&record.created_on, "%Y-%m-%dT%H:%M:%S")
+				.ok()
+				.map(|time| Utc.from_utc_datetime(&time))
+		}))
+}
+
+/// Lets the user pick one of the `candidates` from a select menu.
+async fn choose(ctx: &Context<'_>, name: &str, candidates: &[Candidate]) -> Result<SteamID> {
+	// Commands like `/compare` might ask more than once, so the ID has to be unique.
+	let menu_id = format!("{}_player_{}", ctx.id(), rand::random::<u32>());
+
+	let reply = ctx
+		.send(|reply| {
+			reply
+				.content(format!(
+					"There are multiple players called `{name}`. Which one do you mean?"
+				))
+				.components(|components| {
+					components.create_action_row(|row| {
+						row.create_select_menu(|menu| {
+							menu.custom_id(&menu_id)
+								.placeholder("Choose a player")
+								.options(|options| {
+									for candidate in candidates {
+										let last_seen = candidate.last_seen.map_or_else(
+											|| String::from("no records"),
+											|time| format!("last seen {}", time.format("%Y-%m-%d")),
+										);
+
+										options.create_option(|option| {
+											option
+												.label(&candidate.name)
+												.value(candidate.steam_id.as_id64())
+												.description(format!(
+													"{} · {last_seen}",
+													candidate.steam_id
+												))
+										});
+									}
+
+									options
+								})
+						})
+					})
+				})
+		})
+		.await?;
+
+	let author_id = ctx.author().id;
+	let Some(interaction) = CollectComponentInteraction::new(ctx)
+		.filter({
+			let menu_id = menu_id.clone();
+			move |interaction| {
+				interaction.data.custom_id == menu_id && interaction.user.id == author_id
+			}
+		})
+		.timeout(TIMEOUT)
+		.await
+	else {
+		reply
+			.edit(*ctx, |reply| {
+				reply
+					.content(format!("Nobody was chosen for `{name}`."))
+					.components(|components| components)
+			})
+			.await?;
+
+		return Err(Error::NoPlayerChosen);
+	};
+
+	let candidate = interaction
+		.data
+		.values
+		.first()
+		.and_then(|value| {
+			candidates
+				.iter()
+				.find(|candidate| candidate.steam_id.as_id64().to_string() == *value)
+		})
+		.ok_or(Error::NoPlayerChosen)?;
+
+	// Remove the menu so nobody can choose again.
+	interaction
+		.create_interaction_response(ctx, |response| {
+			response
+				.kind(InteractionResponseType::UpdateMessage)
+				.interaction_response_data(|data| {
+					data.content(format!(
+						"Showing results for **{}** ({}).",
+						candidate.name, candidate.steam_id
+					))
+					.components(|components| components)
+				})
+		})
+		.await?;
+
+	Ok(candidate.steam_id)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn candidate(name: &str, id32: u32) -> Candidate {
+		Candidate {
+			name: name.to_owned(),
+			steam_id: SteamID::from_id32(id32),
+			last_seen: None,
+		}
+	}
+
+	#[test]
+	fn picking() {
+		assert_eq!(pick(Vec::<Candidate>::new(), "AlphaKeks"), Pick::Nobody);
+
+		let alphakeks = candidate("AlphaKeks", 322356345);
+		assert_eq!(pick(vec![alphakeks.clone()], "alpha"), Pick::Player(alphakeks.clone()));
+
+		let alpha = candidate("Alpha", 1);
+		let alphabet = candidate("Alphabet", 2);
+		assert_eq!(
+			pick(
+				vec![
+					alphakeks.clone(),
+					alpha.clone(),
+					alphabet.clone()
+				],
+				"ALPHA"
+			),
+			Pick::Player(alpha.clone())
+		);
+
+		let candidates = vec![alphakeks, alphabet];
+		assert_eq!(pick(candidates.clone(), "alph"), Pick::Ambiguous(candidates));
+
+		// Two players with the exact same name are still ambiguous.
+		let candidates = vec![alpha.clone(), candidate("alpha", 3)];
+		assert_eq!(pick(candidates.clone(), "alpha"), Pick::Ambiguous(candidates));
+	}
+}
@@ -0,0 +1,217 @@
+//! Background task that regularly saves the stats of every linked user, so `/progress` can show how
+//! they changed over time.
+
+use {
+	crate::{
+		circuit_breaker::UpstreamError,
+		database::UserRepository,
+		error::{Error, Result},
+		state::State,
+		stats::PlayerStats,
+	},
+	chrono::{DateTime, Utc},
+	gokz_rs::{Mode, PlayerIdentifier, SteamID},
+	sqlx::{FromRow, Pool, Postgres},
+	std::time::Duration,
+	tracing::{error, info, trace},
+};
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SnapshotRow {
+	tp_points: i32,
+	pro_points: i32,
+	tp_wrs: i32,
+	pro_wrs: i32,
+	tp_completions: Vec<i32>,
+	pro_completions: Vec<i32>,
+	created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+	pub stats: PlayerStats,
+	pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<SnapshotRow> for Snapshot {
+	type Error = Error;
+
+	fn try_from(row: SnapshotRow) -> Result<Self> {
+		let completions = |col: &str, values: Vec<i32>| -> Result<[u32; 8]> {
+			values
+				.into_iter()
+				.map(u32::try_from)
+				.collect::<std::result::Result<Vec<_>, _>>()?
+				.try_into()
+				.map_err(|_| Error::BadDbRow { col: String::from(col) })
+		};
+
+		let tp_completions = completions("tp_completions", row.tp_completions)?;
+		let pro_completions = completions("pro_completions", row.pro_completions)?;
+
+		Ok(Self {
+			stats: PlayerStats {
+				tp_points: row.tp_points.try_into()?,
+				pro_points: row.pro_points.try_into()?,
+				completions: std::array::from_fn(|i| (tp_completions[i], pro_completions[i])),
+				tp_wrs: row.tp_wrs.try_into()?,
+				pro_wrs: row.pro_wrs.try_into()?,
+			},
+			created_at: row.created_at,
+		})
+	}
+}
+
+/// Saves the current stats of a player.
+pub async fn save_snapshot(
+	steam_id: SteamID,
+	mode: Mode,
+	stats: &PlayerStats,
+	db: &Pool<Postgres>,
+) -> Result<()> {
+	sqlx::query(
+		r#"
+		INSERT INTO progress_snapshots
+		    (steam_id, mode, tp_points, pro_points, tp_wrs, pro_wrs, tp_completions, pro_completions)
+		VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+		"#,
+	)
+	.bind(steam_id.as_id32() as i64)
+	.bind(mode as u8 as i16)
+	.bind(stats.tp_points as i32)
+	.bind(stats.pro_points as i32)
+	.bind(stats.tp_wrs as i32)
+	.bind(stats.pro_wrs as i32)
+	.bind(
+		stats
+			.completions
+			.map(|(tp, _)| tp as i32)
+			.to_vec(),
+	)
+	.bind(
+		stats
+			.completions
+			.map(|(_, pro)| pro as i32)
+			.to_vec(),
+	)
+	.execute(db)
+	.await?;
+
+	Ok(())
+}
+
+/// All snapshots of a player since `since`, oldest first. The most recent snapshot from before
+/// `since` is included as well (if there is one), so the first entry is the baseline to compare
+/// against.
+pub async fn fetch_snapshots(
+	steam_id: SteamID,
+	mode: Mode,
+	since: DateTime<Utc>,
+	db: &Pool<Postgres>,
+) -> Result<Vec<Snapshot>> {
+	sqlx::query_as::<_, SnapshotRow>(
+		r#"
+		SELECT * FROM progress_snapshots
+		WHERE steam_id = $1 AND mode = $2 AND created_at >= COALESCE(
+		    (
+		        SELECT MAX(created_at) FROM progress_snapshots
+		        WHERE steam_id = $1 AND mode = $2 AND created_at <= $3
+		    ),
+		    $3
+		)
+		ORDER BY created_at ASC
+		"#,
+	)
+	.bind(steam_id.as_id32() as i64)
+	.bind(mode as u8 as i16)
+	.bind(since)
+	.fetch_all(db)
+	.await?
+	.into_iter()
+	.map(Snapshot::try_from)
+	.collect()
+}
+
+/// Spawns the background task that takes a snapshot of every linked user every `interval`.
+pub fn spawn(state: State) {
+	tokio::spawn(async move {
+		let mut interval =
+			tokio::time::interval(Duration::from_secs(state.config.progress_snapshot_interval));
+
+		loop {
+			interval.tick().await;
+
+			trace!("Taking progress snapshots.");
+
+			let users = match state.users.get_linked().await {
+				Ok(users) => users,
+				Err(why) => {
+					error!("Failed to fetch users for progress snapshots: {why:?}");
+					continue;
+				}
+			};
+
+			let mut saved = 0;
+
+			for steam_id in users
+				.into_iter()
+				.filter_map(|user| user.steam_id)
+			{
+				for mode in [
+					Mode::KZTimer,
+					Mode::SimpleKZ,
+					Mode::Vanilla,
+				] {
+					match snapshot(steam_id, mode, &state).await {
+						Ok(true) => saved += 1,
+						Ok(false) => {}
+						Err(why) => {
+							error!("Failed to take progress snapshot for `{steam_id}`: {why:?}");
+						}
+					}
+				}
+			}
+
+			info!("Saved {saved} progress snapshots.");
+		}
+	});
+}
+
+/// Takes a snapshot of a single player. Returns `false` if they don't have any records in `mode`.
+async fn snapshot(steam_id: SteamID, mode: Mode, state: &State) -> Result<bool> {
+	let mut records = Vec::with_capacity(2);
+
+	for runtype in [true, false] {
+		let player: PlayerIdentifier = steam_id.into();
+		let runtype_records = state
+			.api_cache
+			.get_player_records(&player, mode, runtype, &state.gokz_client)
+			.await
+			.or_else(|why| match why {
+				// The API answered, the player just doesn't have any records.
+				Error::GOKZ { ref error } if !error.is_upstream_failure() => Ok(Vec::new()),
+				// Don't save a snapshot full of zeroes just because the API is having problems.
+				_ => Err(why),
+			})?;
+
+		records.push(runtype_records);
+	}
+
+	let (tp, pro) = (&records[0], &records[1]);
+
+	if tp.is_empty() && pro.is_empty() {
+		return Ok(false);
+	}
+
+	let stats = PlayerStats::new(
+		tp.iter()
+			.map(|record| (record.map_id, record.points)),
+		pro.iter()
+			.map(|record| (record.map_id, record.points)),
+		&state.global_maps.maps(),
+	);
+
+	save_snapshot(steam_id, mode, &stats, &state.database_connection).await?;
+
+	Ok(true)
+}
@@ -0,0 +1,60 @@
+//! Maps that users have recently looked up, so map suggestions can show them first.
+
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::{Arc, Mutex, MutexGuard},
+};
+
+/// How many maps we remember per user.
+const CAPACITY: usize = 10;
+
+#[derive(Debug, Clone, Default)]
+pub struct RecentMaps {
+	maps: Arc<Mutex<HashMap<u64, VecDeque<String>>>>,
+}
+
+impl RecentMaps {
+	/// Remembers that the user with the given `discord_id` has just looked up `map_name`.
+	pub fn remember(&self, discord_id: u64, map_name: &str) {
+		let mut maps = self.lock();
+		let maps = maps.entry(discord_id).or_default();
+
+		maps.retain(|known| known != map_name);
+		maps.push_front(map_name.to_owned());
+		maps.truncate(CAPACITY);
+	}
+
+	/// The maps the user with the given `discord_id` has looked up, most recent first.
+	pub fn get(&self, discord_id: u64) -> Vec<String> {
+		self.lock()
+			.get(&discord_id)
+			.map(|maps| maps.iter().cloned().collect())
+			.unwrap_or_default()
+	}
+
+	fn lock(&self) -> MutexGuard<'_, HashMap<u64, VecDeque<String>>> {
+		// The worst that can happen is a half-updated list of suggestions.
+		self.maps
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn per_user() {
+		let recent_maps = RecentMaps::default();
+
+		recent_maps.remember(1, "kz_lionharder");
+		recent_maps.remember(1, "kz_erratum_v2");
+		recent_maps.remember(1, "kz_lionharder");
+		recent_maps.remember(2, "kz_beginnerblock_go");
+
+		assert_eq!(recent_maps.get(1), vec!["kz_lionharder", "kz_erratum_v2"]);
+		assert_eq!(recent_maps.get(2), vec!["kz_beginnerblock_go"]);
+		assert!(recent_maps.get(3).is_empty());
+	}
+}
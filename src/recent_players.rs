@@ -0,0 +1,72 @@
+//! Players that have recently been looked up with commands like `/pb` or `/profile`, so we can
+//! suggest them when someone types into a `player` option.
+
+use {
+	gokz_rs::SteamID,
+	std::{
+		collections::VecDeque,
+		sync::{Arc, Mutex, MutexGuard},
+	},
+};
+
+/// How many players we remember.
+const CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, Default)]
+pub struct RecentPlayers {
+	players: Arc<Mutex<VecDeque<(String, SteamID)>>>,
+}
+
+impl RecentPlayers {
+	/// Remembers that `steam_id` has just been looked up. If we already know about that player,
+	/// they are moved to the front and their name is updated.
+	pub fn remember(&self, name: &str, steam_id: SteamID) {
+		let mut players = self.lock();
+
+		players.retain(|(_, known)| *known != steam_id);
+		players.push_front((name.to_owned(), steam_id));
+		players.truncate(CAPACITY);
+	}
+
+	/// Every player we remember, most recently looked up first.
+	pub fn get(&self) -> Vec<(String, SteamID)> {
+		self.lock().iter().cloned().collect()
+	}
+
+	fn lock(&self) -> MutexGuard<'_, VecDeque<(String, SteamID)>> {
+		// The worst that can happen is a half-updated list of suggestions.
+		self.players
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn most_recent_first() {
+		let recent_players = RecentPlayers::default();
+		let alphakeks = SteamID::from_id32(322356345);
+		let other = SteamID::from_id32(1);
+
+		recent_players.remember("AlphaKeks", alphakeks);
+		recent_players.remember("other", other);
+		recent_players.remember("AlphaKeks 2", alphakeks);
+
+		assert_eq!(
+			recent_players.get(),
+			vec![
+				(String::from("AlphaKeks 2"), alphakeks),
+				(String::from("other"), other)
+			]
+		);
+
+		for id32 in 0..CAPACITY as u32 * 2 {
+			recent_players.remember("player", SteamID::from_id32(id32));
+		}
+
+		assert_eq!(recent_players.get().len(), CAPACITY);
+	}
+}
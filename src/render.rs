@@ -0,0 +1,389 @@
+//! Renders PNG images that get attached to embeds.
+//!
+//! Text-based bars made out of `█` and `░` look fine on desktop, but break as soon as the embed
+//! gets narrow, e.g. on mobile. Images always look the same.
+
+use {
+	crate::error::Result,
+	chrono::{DateTime, Utc},
+	image::{imageops::FilterType, DynamicImage, ImageOutputFormat, Rgba, RgbaImage},
+	imageproc::{
+		drawing::{
+			draw_filled_circle_mut, draw_filled_rect_mut, draw_line_segment_mut, draw_text_mut,
+			text_size,
+		},
+		rect::Rect,
+	},
+	lazy_static::lazy_static,
+	rusttype::{Font, Scale},
+	std::{io::Cursor, time::Duration},
+	tracing::warn,
+};
+
+lazy_static! {
+	static ref FONT: Font<'static> =
+		Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSans.ttf"))
+			.expect("Failed to load font.");
+	static ref BOLD_FONT: Font<'static> =
+		Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf"))
+			.expect("Failed to load font.");
+}
+
+const BACKGROUND: Rgba<u8> = Rgba([30, 30, 46, 255]);
+const SURFACE: Rgba<u8> = Rgba([69, 71, 90, 255]);
+const TEXT: Rgba<u8> = Rgba([205, 214, 244, 255]);
+const SUBTEXT: Rgba<u8> = Rgba([166, 173, 200, 255]);
+
+const AVATAR_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Everything that's shown on a `/profile` card.
+#[derive(Debug, Clone)]
+pub struct ProfileCard {
+	pub player_name: String,
+	pub mode: String,
+	pub rank: String,
+	pub points: String,
+	pub tp_wrs: u32,
+	pub pro_wrs: u32,
+
+	/// `(TP, PRO)` completions. Index `0` is the total, index `1..=7` are the tiers.
+	pub completions: [(u32, u32); 8],
+
+	/// How many maps there are to complete, in the same layout as `completions`.
+	pub max_completions: [(u32, u32); 8],
+
+	pub avatar: Option<DynamicImage>,
+	pub color: (u8, u8, u8),
+}
+
+impl ProfileCard {
+	const WIDTH: u32 = 800;
+	const HEIGHT: u32 = 440;
+	const BAR_WIDTH: u32 = 250;
+
+	pub fn render(&self) -> Result<Vec<u8>> {
+		let accent = Rgba([
+			self.color.0, self.color.1, self.color.2, 255,
+		]);
+		let mut canvas = RgbaImage::from_pixel(Self::WIDTH, Self::HEIGHT, BACKGROUND);
+
+		draw_filled_rect_mut(&mut canvas, Rect::at(0, 0).of_size(8, Self::HEIGHT), accent);
+
+		let text_x = match &self.avatar {
+			None => 32,
+			Some(avatar) => {
+				let avatar = avatar.resize_exact(112, 112, FilterType::Triangle);
+				image::imageops::overlay(&mut canvas, &avatar.to_rgba8(), 32, 28);
+				164
+			}
+		};
+
+		draw_text_mut(
+			&mut canvas,
+			TEXT,
+			text_x,
+			28,
+			Scale::uniform(38.0),
+			&BOLD_FONT,
+			&self.player_name,
+		);
+
+		draw_text_mut(
+			&mut canvas,
+			accent,
+			text_x,
+			78,
+			Scale::uniform(24.0),
+			&FONT,
+			&format!("[{}] {} · {} points", self.mode, self.rank, self.points),
+		);
+
+		draw_text_mut(
+			&mut canvas,
+			SUBTEXT,
+			text_x,
+			110,
+			Scale::uniform(20.0),
+			&FONT,
+			&format!("WRs: {} TP · {} PRO", self.tp_wrs, self.pro_wrs),
+		);
+
+		let columns = [
+			(96, "TP"),
+			(96 + Self::BAR_WIDTH as i32 + 110, "PRO"),
+		];
+
+		for (i, (x, label)) in columns.into_iter().enumerate() {
+			let (count, max) = match i {
+				0 => (self.completions[0].0, self.max_completions[0].0),
+				_ => (self.completions[0].1, self.max_completions[0].1),
+			};
+
+			draw_text_mut(
+				&mut canvas,
+				TEXT,
+				x,
+				164,
+				Scale::uniform(22.0),
+				&BOLD_FONT,
+				&format!("{label}  {count}/{max} ({:.2}%)", percentage(count, max)),
+			);
+		}
+
+		for tier in 1..8 {
+			let y = 168 + tier as i32 * 34;
+
+			draw_text_mut(
+				&mut canvas,
+				SUBTEXT,
+				32,
+				y - 2,
+				Scale::uniform(20.0),
+				&FONT,
+				&format!("T{tier}"),
+			);
+
+			for (i, (x, _)) in columns.into_iter().enumerate() {
+				let (count, max) = match i {
+					0 => (self.completions[tier].0, self.max_completions[tier].0),
+					_ => (self.completions[tier].1, self.max_completions[tier].1),
+				};
+
+				draw_bar(&mut canvas, x, y, Self::BAR_WIDTH, percentage(count, max), accent);
+
+				draw_text_mut(
+					&mut canvas,
+					SUBTEXT,
+					x + Self::BAR_WIDTH as i32 + 10,
+					y - 2,
+					Scale::uniform(18.0),
+					&FONT,
+					&format!("{count}/{max}"),
+				);
+			}
+		}
+
+		encode(canvas)
+	}
+}
+
+/// Renders a line chart of a player's points over time, as shown by `/progress`.
+pub fn points_chart(points: &[(DateTime<Utc>, u32)], color: (u8, u8, u8)) -> Result<Vec<u8>> {
+	const WIDTH: u32 = 800;
+	const HEIGHT: u32 = 320;
+	const LEFT: f32 = 96.0;
+	const RIGHT: f32 = WIDTH as f32 - 32.0;
+	const TOP: f32 = 32.0;
+	const BOTTOM: f32 = HEIGHT as f32 - 48.0;
+
+	let accent = Rgba([color.0, color.1, color.2, 255]);
+	let mut canvas = RgbaImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+
+	let min = points
+		.iter()
+		.map(|(_, points)| *points)
+		.min()
+		.unwrap_or_default();
+
+	let max = points
+		.iter()
+		.map(|(_, points)| *points)
+		.max()
+		.unwrap_or_default();
+
+	// Axes
+	draw_line_segment_mut(&mut canvas, (LEFT, TOP), (LEFT, BOTTOM), SURFACE);
+	draw_line_segment_mut(&mut canvas, (LEFT, BOTTOM), (RIGHT, BOTTOM), SURFACE);
+
+	for (value, y) in [(max, TOP), (min, BOTTOM)] {
+		let label = value.to_string();
+		let (width, height) = text_size(Scale::uniform(18.0), &FONT, &label);
+
+		draw_text_mut(
+			&mut canvas,
+			SUBTEXT,
+			LEFT as i32 - width - 10,
+			y as i32 - height / 2,
+			Scale::uniform(18.0),
+			&FONT,
+			&label,
+		);
+	}
+
+	if let (Some((first, _)), Some((last, _))) = (points.first(), points.last()) {
+		let first = first.format("%Y-%m-%d").to_string();
+		let last = last.format("%Y-%m-%d").to_string();
+		let (width, _) = text_size(Scale::uniform(18.0), &FONT, &last);
+
+		draw_text_mut(
+			&mut canvas,
+			SUBTEXT,
+			LEFT as i32,
+			BOTTOM as i32 + 12,
+			Scale::uniform(18.0),
+			&FONT,
+			&first,
+		);
+
+		draw_text_mut(
+			&mut canvas,
+			SUBTEXT,
+			RIGHT as i32 - width,
+			BOTTOM as i32 + 12,
+			Scale::uniform(18.0),
+			&FONT,
+			&last,
+		);
+	}
+
+	let start = points
+		.first()
+		.map(|(date, _)| date.timestamp())
+		.unwrap_or_default();
+
+	let end = points
+		.last()
+		.map(|(date, _)| date.timestamp())
+		.unwrap_or_default();
+
+	let coordinates = points
+		.iter()
+		.map(|(date, points)| {
+			let x = match end - start {
+				0 => RIGHT,
+				range => LEFT + (date.timestamp() - start) as f32 / range as f32 * (RIGHT - LEFT),
+			};
+
+			let y = match max - min {
+				0 => TOP,
+				range => BOTTOM - (points - min) as f32 / range as f32 * (BOTTOM - TOP),
+			};
+
+			(x, y)
+		})
+		.collect::<Vec<_>>();
+
+	for line in coordinates.windows(2) {
+		let ((x1, y1), (x2, y2)) = (line[0], line[1]);
+
+		// Draw the line a few times to make it thicker.
+		for offset in [-1.0, 0.0, 1.0] {
+			draw_line_segment_mut(&mut canvas, (x1, y1 + offset), (x2, y2 + offset), accent);
+		}
+	}
+
+	for (x, y) in coordinates {
+		draw_filled_circle_mut(&mut canvas, (x as i32, y as i32), 4, accent);
+	}
+
+	encode(canvas)
+}
+
+/// Downloads a player's avatar so it can be drawn onto a card. A card without an avatar is better
+/// than no card at all, so we give up after [`AVATAR_TIMEOUT`].
+pub async fn fetch_avatar(url: &str, client: &gokz_rs::Client) -> Option<DynamicImage> {
+	if url.is_empty() {
+		return None;
+	}
+
+	let bytes = match client
+		.get(url)
+		.timeout(AVATAR_TIMEOUT)
+		.send()
+		.await
+	{
+		Ok(response) => response.bytes().await.ok()?,
+		Err(why) => {
+			warn!("Failed to fetch avatar from `{url}`: {why:?}");
+			return None;
+		}
+	};
+
+	image::load_from_memory(&bytes)
+		.map_err(|why| warn!("Failed to decode avatar from `{url}`: {why:?}"))
+		.ok()
+}
+
+fn draw_bar(canvas: &mut RgbaImage, x: i32, y: i32, width: u32, percentage: f64, color: Rgba<u8>) {
+	const HEIGHT: u32 = 18;
+
+	draw_filled_rect_mut(canvas, Rect::at(x, y).of_size(width, HEIGHT), SURFACE);
+
+	let filled = ((percentage / 100f64) * width as f64).clamp(0f64, width as f64) as u32;
+
+	if filled > 0 {
+		draw_filled_rect_mut(canvas, Rect::at(x, y).of_size(filled, HEIGHT), color);
+	}
+}
+
+fn percentage(count: u32, max: u32) -> f64 {
+	match max {
+		0 => 0f64,
+		max => (count as f64 / max as f64) * 100f64,
+	}
+}
+
+fn encode(canvas: RgbaImage) -> Result<Vec<u8>> {
+	let mut bytes = Vec::new();
+
+	DynamicImage::ImageRgba8(canvas)
+		.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+
+	Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PNG_SIGNATURE: [u8; 8] = [
+		0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A,
+	];
+
+	#[test]
+	fn profile_card() {
+		let card = ProfileCard {
+			player_name: String::from("AlphaKeks"),
+			mode: String::from("SKZ"),
+			rank: String::from("Pro"),
+			points: String::from("123,456"),
+			tp_wrs: 3,
+			pro_wrs: 1,
+			completions: [
+				(120, 80),
+				(40, 30),
+				(30, 20),
+				(20, 15),
+				(15, 10),
+				(10, 5),
+				(5, 0),
+				(0, 0),
+			],
+			max_completions: [(500, 450); 8],
+			avatar: Some(DynamicImage::new_rgba8(184, 184)),
+			color: (116, 128, 194),
+		};
+
+		let png = card.render().unwrap();
+		assert!(png.starts_with(&PNG_SIGNATURE));
+	}
+
+	#[test]
+	fn chart() {
+		let now = Utc::now();
+		let points = [
+			(now - chrono::Duration::days(2), 1000),
+			(now - chrono::Duration::days(1), 1500),
+			(now, 1500),
+		];
+
+		assert!(points_chart(&points, (116, 128, 194))
+			.unwrap()
+			.starts_with(&PNG_SIGNATURE));
+
+		// Nothing to draw, but it should still work.
+		assert!(points_chart(&[], (116, 128, 194))
+			.unwrap()
+			.starts_with(&PNG_SIGNATURE));
+	}
+}
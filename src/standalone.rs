@@ -0,0 +1,81 @@
+//! Runs the bot as a standalone binary, without shuttle.rs.
+//!
+//! The config is read from a TOML file with the same keys as `Secrets.example.toml`. Its path can
+//! be passed as the first argument or via the `CONFIG_FILE` environment variable. If neither is
+//! present, the config is read from environment variables instead.
+
+use {
+	crate::{config::Config, state::State},
+	std::sync::Arc,
+	tracing::{error, info},
+	tracing_subscriber::EnvFilter,
+};
+
+pub async fn run() {
+	tracing_subscriber::fmt()
+		.with_env_filter(
+			EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+		)
+		.init();
+
+	let config = match std::env::args()
+		.nth(1)
+		.or_else(|| std::env::var("CONFIG_FILE").ok())
+	{
+		Some(path) => Config::from_file(path),
+		None => Config::from_env(),
+	};
+
+	let state = State::new(config).await;
+	let database_connection = state.database_connection.clone();
+
+	let framework = crate::framework(state)
+		.build()
+		.await
+		.expect("Failed to build framework.");
+
+	let shard_manager = Arc::clone(framework.shard_manager());
+
+	tokio::spawn(async move {
+		shutdown_signal().await;
+		info!("Shutting down...");
+		shard_manager
+			.lock()
+			.await
+			.shutdown_all()
+			.await;
+	});
+
+	if let Err(why) = framework.start().await {
+		error!("Failed to run SchnoseBot: {why:?}");
+	}
+
+	database_connection.close().await;
+
+	info!("Goodbye!");
+}
+
+/// Resolves once we receive either `SIGINT` or `SIGTERM`.
+async fn shutdown_signal() {
+	let ctrl_c = async {
+		tokio::signal::ctrl_c()
+			.await
+			.expect("Failed to listen for SIGINT.");
+	};
+
+	#[cfg(unix)]
+	let terminate = async {
+		tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+			.expect("Failed to listen for SIGTERM.")
+			.recv()
+			.await;
+	};
+
+	#[cfg(not(unix))]
+	let terminate = std::future::pending::<()>();
+
+	tokio::select! {
+		_ = ctrl_c => {},
+		_ = terminate => {},
+	}
+}
@@ -1,14 +1,25 @@
 use {
 	crate::{
+		api_cache::ApiCache,
 		config::Config,
-		database,
+		database::{self, UserRepository},
 		error::{Error, Result},
+		global_maps::MapCache,
+		guild_settings::GuildSettings,
+		recent_maps::RecentMaps,
+		recent_players::RecentPlayers,
+		steam::{SteamClient, SteamWebClient},
 		target::Target,
 	},
 	gokz_rs::{MapIdentifier, Mode, SteamID},
-	poise::async_trait,
+	num_format::Locale,
+	poise::{
+		async_trait,
+		serenity_prelude::{GuildId, Http},
+	},
 	schnosebot::global_map::GlobalMap,
-	sqlx::{postgres::PgPoolOptions, Pool, Postgres, QueryBuilder},
+	sqlx::{postgres::PgPoolOptions, Pool, Postgres},
+	std::{collections::HashSet, sync::Arc, time::Duration},
 	tracing::error,
 };
 
@@ -35,11 +46,26 @@ pub struct State {
 	/// Postgres connection pool for storing user data
 	pub database_connection: Pool<Postgres>,
 
-	/// Cache of all global maps
-	pub global_maps: Vec<GlobalMap>,
+	/// Access to the users table
+	pub users: database::PgUserRepository,
+
+	/// Settings of every server, kept in sync with the database by `/config`
+	pub guild_settings: GuildSettings,
+
+	/// Cache of all global maps, refreshed periodically in the background
+	pub global_maps: MapCache,
+
+	/// Cache for API responses
+	pub api_cache: ApiCache,
+
+	/// HTTP Client for the Steam Web API
+	pub steam: SteamWebClient,
 
-	/// Cache of all global map names
-	pub global_maps_names: Vec<String>,
+	/// Players that have recently been looked up, for autocompletion
+	pub recent_players: RecentPlayers,
+
+	/// Maps that users have recently looked up, for autocompletion
+	pub recent_maps: RecentMaps,
 }
 
 impl State {
@@ -58,14 +84,32 @@ impl State {
 			.await
 			.expect("Failed to connect to database.");
 
-		let global_maps = GlobalMap::fetch(true, &gokz_client)
+		database::migrate(&database_connection)
+			.await
+			.expect("Failed to migrate database.");
+
+		let users = database::PgUserRepository::new(database_connection.clone());
+
+		let guild_settings = GuildSettings::load(&database_connection)
+			.await
+			.expect("Failed to load server settings.");
+
+		let global_maps = MapCache::new(&gokz_client)
 			.await
 			.expect("Failed to fetch global maps.");
 
-		let global_maps_names = global_maps
-			.iter()
-			.map(|map| map.name.clone())
-			.collect();
+		global_maps.spawn_refresh_task(
+			gokz_client.clone(),
+			Duration::from_secs(config.map_refresh_interval),
+		);
+
+		let api_cache = ApiCache::new();
+		api_cache.spawn_health_check(
+			gokz_client.clone(),
+			Duration::from_secs(config.health_check_interval),
+		);
+
+		let steam = SteamWebClient::new(config.steam_token.clone());
 
 		Self {
 			config,
@@ -74,8 +118,13 @@ impl State {
 			color,
 			gokz_client,
 			database_connection,
+			users,
+			guild_settings,
 			global_maps,
-			global_maps_names,
+			api_cache,
+			steam,
+			recent_players: RecentPlayers::default(),
+			recent_maps: RecentMaps::default(),
 		}
 	}
 }
@@ -87,15 +136,26 @@ pub trait StateContainer {
 	fn icon_url(&self) -> &str;
 	fn color(&self) -> (u8, u8, u8);
 	fn gokz_client(&self) -> &gokz_rs::Client;
+	fn api_cache(&self) -> &ApiCache;
 	fn db(&self) -> &Pool<Postgres>;
-	fn maps(&self) -> &[GlobalMap];
-	fn map_names(&self) -> &[String];
+	fn users(&self) -> &database::PgUserRepository;
+	fn guild_settings(&self) -> &GuildSettings;
+	fn steam(&self) -> &SteamWebClient;
+	fn recent_players(&self) -> &RecentPlayers;
+	fn recent_maps(&self) -> &RecentMaps;
+	fn maps(&self) -> Arc<Vec<GlobalMap>>;
+	fn map_names(&self) -> Arc<Vec<String>>;
+
+	/// Looks up a global map. Commands should remember the map in [`StateContainer::recent_maps`]
+	/// once they found it, so it shows up first in the author's map suggestions. Autocompletion
+	/// uses this too, so it can't do that itself.
 	fn get_map(&self, map_identifier: impl Into<MapIdentifier>) -> Result<GlobalMap>;
 
 	fn author_id(&self) -> u64;
 
-	async fn fetch_user(&self, target: Target) -> Option<database::User> {
-		match target {
+	/// The database entry of `target`. Custom profile URLs are resolved through Steam first.
+	async fn fetch_user(&self, target: Target) -> Result<Option<database::User>> {
+		Ok(match target {
 			Target::None { user_id } | Target::Mention { user_id } => {
 				self.fetch_user_by_id(user_id).await
 			}
@@ -103,13 +163,42 @@ pub trait StateContainer {
 				self.fetch_user_by_steam_id(steam_id)
 					.await
 			}
+			Target::VanityURL { vanity_url } => {
+				let steam_id = self
+					.steam()
+					.resolve_vanity_url(&vanity_url)
+					.await?
+					.ok_or(Error::PlayerNotFound { input: vanity_url })?;
+
+				self.fetch_user_by_steam_id(steam_id)
+					.await
+			}
 			Target::Name { name } => self.fetch_user_by_name(&name).await,
-		}
+		})
 	}
 	async fn fetch_user_by_id(&self, discord_id: u64) -> Option<database::User>;
 	async fn fetch_user_by_name(&self, username: &str) -> Option<database::User>;
 	async fn fetch_user_by_steam_id(&self, steam_id: SteamID) -> Option<database::User>;
 	async fn fetch_user_by_mode(&self, mode: Mode) -> Option<database::User>;
+
+	/// Settings of the server the command was used in. These are cached, so this never waits for
+	/// the database.
+	async fn fetch_guild(&self) -> Option<database::Guild>;
+
+	/// The locale numbers should be formatted with, as set with `/config`. Defaults to English.
+	async fn locale(&self) -> Locale;
+
+	/// All members of the server the command was used in who have a verified SteamID set.
+	async fn fetch_verified_members(&self) -> Result<Vec<database::User>>;
+
+	/// A player's avatar. KZ:GO is asked first, Steam if that fails.
+	async fn fetch_avatar(&self, steam_id: SteamID) -> Option<String>;
+
+	/// A player's Steam name, for when none of the KZ APIs know about them.
+	async fn fetch_steam_name(&self, steam_id: SteamID) -> Option<String>;
+
+	/// Defers the response, respecting the server's `ephemeral` setting if there is one.
+	async fn defer_reply(&self) -> Result<()>;
 }
 
 #[async_trait]
@@ -134,21 +223,45 @@ impl StateContainer for Context<'_> {
 		&self.data().gokz_client
 	}
 
+	fn api_cache(&self) -> &ApiCache {
+		&self.data().api_cache
+	}
+
 	fn db(&self) -> &Pool<Postgres> {
 		&self.data().database_connection
 	}
 
-	fn maps(&self) -> &[GlobalMap] {
-		&self.data().global_maps
+	fn users(&self) -> &database::PgUserRepository {
+		&self.data().users
+	}
+
+	fn guild_settings(&self) -> &GuildSettings {
+		&self.data().guild_settings
 	}
 
-	fn map_names(&self) -> &[String] {
-		&self.data().global_maps_names
+	fn steam(&self) -> &SteamWebClient {
+		&self.data().steam
+	}
+
+	fn recent_players(&self) -> &RecentPlayers {
+		&self.data().recent_players
+	}
+
+	fn recent_maps(&self) -> &RecentMaps {
+		&self.data().recent_maps
+	}
+
+	fn maps(&self) -> Arc<Vec<GlobalMap>> {
+		self.data().global_maps.maps()
+	}
+
+	fn map_names(&self) -> Arc<Vec<String>> {
+		self.data().global_maps.names()
 	}
 
 	fn get_map(&self, map_identifier: impl Into<MapIdentifier>) -> Result<GlobalMap> {
 		let map_identifier = map_identifier.into();
-		GlobalMap::fuzzy_search(self.maps(), map_identifier.clone())
+		GlobalMap::fuzzy_search(&self.maps(), map_identifier.clone())
 			.ok_or(Error::MapNotGlobal { input: map_identifier.to_string() })
 	}
 
@@ -157,100 +270,136 @@ impl StateContainer for Context<'_> {
 	}
 
 	async fn fetch_user_by_id(&self, discord_id: u64) -> Option<database::User> {
-		let table_name = &self.config().users_table;
-
-		match sqlx::query_as::<_, database::UserRow>(&format!(
-			"SELECT * FROM {table_name} WHERE discord_id = {discord_id}"
-		))
-		.fetch_optional(self.db())
-		.await
-		{
-			Ok(row) => match row?.try_into() {
-				Ok(user) => Some(user),
-				Err(why) => {
-					error!("Failed to parse user: {why:?}");
-					None
-				}
-			},
-			Err(why) => {
-				error!("Failed to fetch user from DB: {why:?}");
-				None
-			}
-		}
+		log_db_error(self.users().get(discord_id).await)
 	}
 
 	async fn fetch_user_by_name(&self, username: &str) -> Option<database::User> {
-		let table_name = &self.config().users_table;
+		log_db_error(self.users().get_by_name(username).await)
+	}
+
+	async fn fetch_user_by_steam_id(&self, steam_id: SteamID) -> Option<database::User> {
+		log_db_error(
+			self.users()
+				.get_by_steam_id(steam_id)
+				.await,
+		)
+	}
+
+	async fn fetch_user_by_mode(&self, mode: Mode) -> Option<database::User> {
+		log_db_error(self.users().get_by_mode(mode).await)
+	}
+
+	async fn fetch_guild(&self) -> Option<database::Guild> {
+		let guild_id = self.guild_id()?;
 
-		let mut query = QueryBuilder::new(format!("SELECT * FROM {table_name}"));
-		query
-			.push(" WHERE name LIKE ")
-			.push_bind(format!("%{username}%"));
+		self.guild_settings()
+			.get(*guild_id.as_u64())
+	}
 
-		match query
-			.build_query_as::<database::UserRow>()
-			.fetch_optional(self.db())
+	async fn locale(&self) -> Locale {
+		self.fetch_guild()
 			.await
-		{
-			Ok(row) => match row?.try_into() {
-				Ok(user) => Some(user),
-				Err(why) => {
-					error!("Failed to parse user: {why:?}");
-					None
-				}
-			},
-			Err(why) => {
-				error!("Failed to fetch user from DB: {why:?}");
-				None
-			}
+			.and_then(|guild| guild.locale)
+			.and_then(|locale| Locale::from_name(locale).ok())
+			.unwrap_or(Locale::en)
+	}
+
+	async fn fetch_verified_members(&self) -> Result<Vec<database::User>> {
+		let Some(guild_id) = self.guild_id() else {
+			return Err(Error::NoVerifiedMembers);
+		};
+
+		let members =
+			fetch_verified_members(&self.serenity_context().http, guild_id, self.users()).await?;
+
+		if members.is_empty() {
+			return Err(Error::NoVerifiedMembers);
 		}
+
+		Ok(members)
 	}
 
-	async fn fetch_user_by_steam_id(&self, steam_id: SteamID) -> Option<database::User> {
-		let table_name = &self.config().users_table;
-
-		match sqlx::query_as::<_, database::UserRow>(&format!(
-			"SELECT * FROM {table_name} WHERE steam_id = {}",
-			steam_id.as_id32()
-		))
-		.fetch_optional(self.db())
-		.await
+	async fn fetch_avatar(&self, steam_id: SteamID) -> Option<String> {
+		if let Ok(avatar_url) = self
+			.api_cache()
+			.get_avatar(steam_id, self.gokz_client())
+			.await
 		{
-			Ok(row) => match row?.try_into() {
-				Ok(user) => Some(user),
-				Err(why) => {
-					error!("Failed to parse user: {why:?}");
-					None
-				}
-			},
-			Err(why) => {
-				error!("Failed to fetch user from DB: {why:?}");
-				None
-			}
+			return Some(avatar_url);
 		}
+
+		self.steam()
+			.get_player_summary(steam_id)
+			.await
+			.ok()
+			.flatten()
+			.map(|player| player.avatarfull)
 	}
 
-	async fn fetch_user_by_mode(&self, mode: Mode) -> Option<database::User> {
-		let table_name = &self.config().users_table;
-
-		match sqlx::query_as::<_, database::UserRow>(&format!(
-			"SELECT * FROM {table_name} WHERE mode = {}",
-			mode as u8
-		))
-		.fetch_optional(self.db())
-		.await
+	async fn fetch_steam_name(&self, steam_id: SteamID) -> Option<String> {
+		self.steam()
+			.get_player_summary(steam_id)
+			.await
+			.ok()
+			.flatten()
+			.map(|player| player.personaname)
+	}
+
+	async fn defer_reply(&self) -> Result<()> {
+		let ephemeral = match self
+			.fetch_guild()
+			.await
+			.and_then(|guild| guild.ephemeral)
 		{
-			Ok(row) => match row?.try_into() {
-				Ok(user) => Some(user),
-				Err(why) => {
-					error!("Failed to parse user: {why:?}");
-					None
-				}
-			},
-			Err(why) => {
-				error!("Failed to fetch user from DB: {why:?}");
-				None
-			}
+			Some(ephemeral) => ephemeral,
+			None => self.command().ephemeral,
+		};
+
+		self.defer_response(ephemeral).await?;
+
+		Ok(())
+	}
+}
+
+/// All members of the server with the given `guild_id` who have a verified SteamID set.
+pub async fn fetch_verified_members(
+	http: &Http,
+	guild_id: GuildId,
+	users: &impl UserRepository,
+) -> Result<Vec<database::User>> {
+	// Discord hands out at most 1000 members per request, so we page through them once instead of
+	// asking about every user individually.
+	let mut member_ids = HashSet::new();
+	let mut after = None;
+
+	loop {
+		let page = guild_id
+			.members(http, Some(1000), after)
+			.await?;
+
+		after = page.last().map(|member| member.user.id);
+		member_ids.extend(
+			page.iter()
+				.map(|member| *member.user.id.as_u64()),
+		);
+
+		if page.len() < 1000 {
+			break;
 		}
 	}
+
+	Ok(users
+		.get_verified()
+		.await?
+		.into_iter()
+		.filter(|user| member_ids.contains(&user.discord_id))
+		.collect())
+}
+
+/// Turns a failed database lookup into `None` so callers can treat it like a missing entry.
+fn log_db_error<T>(result: Result<Option<T>>) -> Option<T> {
+	result.unwrap_or_else(|why| {
+		error!("Failed to fetch user from DB: {why:?}");
+		None
+	})
 }
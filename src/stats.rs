@@ -0,0 +1,126 @@
+//! Aggregated stats about a player's records in a single mode, as shown by `/profile` and tracked
+//! over time by `/progress`.
+
+use {schnosebot::global_map::GlobalMap, std::collections::HashMap};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerStats {
+	pub tp_points: u32,
+	pub pro_points: u32,
+
+	/// `(TP, PRO)` completions. Index `0` is the total, index `1..=7` are the tiers.
+	pub completions: [(u32, u32); 8],
+
+	pub tp_wrs: u32,
+	pub pro_wrs: u32,
+}
+
+impl PlayerStats {
+	/// Sums up a player's records. `tp` and `pro` are `(map_id, points)` pairs. Records on maps
+	/// that are not in `maps` are ignored, and only the first record per map counts.
+	pub fn new(
+		tp: impl IntoIterator<Item = (u16, u32)>,
+		pro: impl IntoIterator<Item = (u16, u32)>,
+		maps: &[GlobalMap],
+	) -> Self {
+		let tiers = maps
+			.iter()
+			.map(|map| (map.id, map.tier as u8))
+			.collect::<HashMap<_, _>>();
+
+		let mut stats = Self::default();
+
+		let mut tp_maps = tiers.clone();
+		for (map_id, points) in tp {
+			if let Some(tier) = tp_maps.remove(&map_id) {
+				stats.tp_points += points;
+				stats.completions[0].0 += 1;
+				stats.completions[tier as usize].0 += 1;
+
+				if points == 1000 {
+					stats.tp_wrs += 1;
+				}
+			}
+		}
+
+		let mut pro_maps = tiers;
+		for (map_id, points) in pro {
+			if let Some(tier) = pro_maps.remove(&map_id) {
+				stats.pro_points += points;
+				stats.completions[0].1 += 1;
+				stats.completions[tier as usize].1 += 1;
+
+				if points == 1000 {
+					stats.pro_wrs += 1;
+				}
+			}
+		}
+
+		stats
+	}
+
+	pub fn total_points(&self) -> u32 {
+		self.tp_points + self.pro_points
+	}
+
+	/// Completion percentages for TP and PRO, given how many maps there are in total.
+	pub fn completion_percentages(&self, max: [(u32, u32); 8]) -> [(f64, f64); 8] {
+		let percentage = |count: u32, max: u32| match (count, max) {
+			(0, _) | (_, 0) => 0f64,
+			(count, max) => (count as f64 / max as f64) * 100f64,
+		};
+
+		let mut percentages = [(0f64, 0f64); 8];
+
+		for (i, percentages) in percentages.iter_mut().enumerate() {
+			percentages.0 = percentage(self.completions[i].0, max[i].0);
+			percentages.1 = percentage(self.completions[i].1, max[i].1);
+		}
+
+		percentages
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn empty() {
+		let stats = PlayerStats::new([], [], &[]);
+
+		assert_eq!(stats, PlayerStats::default());
+		assert_eq!(stats.completion_percentages([(10, 10); 8]), [(0f64, 0f64); 8]);
+	}
+
+	#[test]
+	fn percentages() {
+		let stats = PlayerStats {
+			completions: [
+				(5, 2),
+				(5, 2),
+				(0, 0),
+				(0, 0),
+				(0, 0),
+				(0, 0),
+				(0, 0),
+				(0, 0),
+			],
+			..Default::default()
+		};
+
+		let percentages = stats.completion_percentages([
+			(10, 10),
+			(10, 0),
+			(0, 0),
+			(0, 0),
+			(0, 0),
+			(0, 0),
+			(0, 0),
+			(0, 0),
+		]);
+
+		assert_eq!(percentages[0], (50f64, 20f64));
+		assert_eq!(percentages[1], (50f64, 0f64));
+	}
+}
@@ -0,0 +1,232 @@
+//! Talking to the [Steam Web API](https://developer.valvesoftware.com/wiki/Steam_Web_API) and
+//! verifying that a SteamID actually belongs to a user.
+//!
+//! `/setsteam` accepts any SteamID. To prove that an account is theirs, a user has to put a code
+//! into their Steam name, which we then check with `/verify`. Steam is also used to resolve
+//! custom profile URLs, and as a fallback for names and avatars when KZ:GO doesn't know a player.
+
+use {
+	crate::error::{Error, Result},
+	gokz_rs::SteamID,
+	poise::async_trait,
+	rand::Rng,
+	serde::Deserialize,
+	std::time::Duration,
+};
+
+const BASE_URL: &str = "https://api.steampowered.com";
+
+/// Commands wait for Steam before they can respond, so we'd rather fail than hang.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A player as returned by `ISteamUser/GetPlayerSummaries`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PlayerSummary {
+	pub personaname: String,
+	pub avatarfull: String,
+}
+
+/// Everything we need from Steam. This is a trait so tests don't have to talk to Steam.
+#[async_trait]
+pub trait SteamClient: Send + Sync {
+	/// Profile information about a player. Returns `None` if there is no such account.
+	async fn get_player_summary(&self, steam_id: SteamID) -> Result<Option<PlayerSummary>>;
+
+	/// The SteamID behind a custom profile URL, e.g. `alphakeks` for
+	/// `https://steamcommunity.com/id/alphakeks`. Returns `None` if there is no such profile.
+	async fn resolve_vanity_url(&self, vanity_url: &str) -> Result<Option<SteamID>>;
+}
+
+/// [`SteamClient`] that talks to the actual Steam Web API.
+#[derive(Debug, Clone)]
+pub struct SteamWebClient {
+	client: reqwest::Client,
+	token: String,
+}
+
+impl SteamWebClient {
+	pub fn new(token: impl Into<String>) -> Self {
+		Self {
+			client: reqwest::Client::builder()
+				.timeout(TIMEOUT)
+				.build()
+				.expect("Failed to build Steam client."),
+			token: token.into(),
+		}
+	}
+
+	fn token(&self) -> Result<&str> {
+		match self.token.as_str() {
+			"" => {
+				Err(Error::Custom(String::from("The bot is not connected to the Steam Web API.")))
+			}
+			token => Ok(token),
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct Response<T> {
+	response: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlayerSummaries {
+	players: Vec<PlayerSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanityURL {
+	/// `1` if the URL was found, `42` if not
+	success: u8,
+	steamid: Option<String>,
+}
+
+#[async_trait]
+impl SteamClient for SteamWebClient {
+	async fn get_player_summary(&self, steam_id: SteamID) -> Result<Option<PlayerSummary>> {
+		let steam_id = steam_id.as_id64().to_string();
+
+		let Response { response: PlayerSummaries { players } } = self
+			.client
+			.get(format!("{BASE_URL}/ISteamUser/GetPlayerSummaries/v2/"))
+			.query(&[
+				("key", self.token()?),
+				("steamids", steam_id.as_str()),
+			])
+			.send()
+			.await?
+			.error_for_status()?
+			.json()
+			.await?;
+
+		Ok(players.into_iter().next())
+	}
+
+	async fn resolve_vanity_url(&self, vanity_url: &str) -> Result<Option<SteamID>> {
+		let Response { response: VanityURL { success, steamid } } = self
+			.client
+			.get(format!("{BASE_URL}/ISteamUser/ResolveVanityURL/v1/"))
+			.query(&[
+				("key", self.token()?),
+				("vanityurl", vanity_url),
+			])
+			.send()
+			.await?
+			.error_for_status()?
+			.json()
+			.await?;
+
+		Ok(match (success, steamid) {
+			(1, Some(steam_id)) => SteamID::new(&steam_id).ok(),
+			_ => None,
+		})
+	}
+}
+
+/// [`SteamClient`] that only knows about the players you give it.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockSteamClient {
+	players: std::sync::Mutex<std::collections::HashMap<u64, PlayerSummary>>,
+	vanity_urls: std::sync::Mutex<std::collections::HashMap<String, SteamID>>,
+}
+
+#[cfg(test)]
+impl MockSteamClient {
+	/// Creates or renames a player.
+	pub fn set_name(&self, steam_id: SteamID, name: &str) {
+		self.players.lock().unwrap().insert(
+			steam_id.as_id64(),
+			PlayerSummary {
+				personaname: name.to_owned(),
+				avatarfull: String::new(),
+			},
+		);
+	}
+
+	/// Points a custom profile URL at `steam_id`.
+	pub fn set_vanity_url(&self, steam_id: SteamID, vanity_url: &str) {
+		self.vanity_urls
+			.lock()
+			.unwrap()
+			.insert(vanity_url.to_owned(), steam_id);
+	}
+}
+
+#[cfg(test)]
+#[async_trait]
+impl SteamClient for MockSteamClient {
+	async fn get_player_summary(&self, steam_id: SteamID) -> Result<Option<PlayerSummary>> {
+		Ok(self
+			.players
+			.lock()
+			.unwrap()
+			.get(&steam_id.as_id64())
+			.cloned())
+	}
+
+	async fn resolve_vanity_url(&self, vanity_url: &str) -> Result<Option<SteamID>> {
+		Ok(self
+			.vanity_urls
+			.lock()
+			.unwrap()
+			.get(vanity_url)
+			.copied())
+	}
+}
+
+/// A new code for a user to put into their Steam name, e.g. `schnose-042069`.
+pub fn verification_code() -> String {
+	format!("schnose-{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
+
+/// Whether the Steam name of `steam_id` currently contains `code`.
+pub async fn check_code(steam: &impl SteamClient, steam_id: SteamID, code: &str) -> Result<bool> {
+	Ok(steam
+		.get_player_summary(steam_id)
+		.await?
+		.map_or(false, |player| player.personaname.contains(code)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn code_in_name() {
+		let steam = MockSteamClient::default();
+		let steam_id = SteamID::from_id32(322356345);
+		let code = verification_code();
+
+		// Unknown account
+		assert_eq!(check_code(&steam, steam_id, &code).await, Ok(false));
+
+		steam.set_name(steam_id, "AlphaKeks");
+		assert_eq!(check_code(&steam, steam_id, &code).await, Ok(false));
+
+		steam.set_name(steam_id, &format!("AlphaKeks {code}"));
+		assert_eq!(check_code(&steam, steam_id, &code).await, Ok(true));
+	}
+
+	#[tokio::test]
+	async fn vanity_url() {
+		let steam = MockSteamClient::default();
+		let steam_id = SteamID::from_id32(322356345);
+
+		assert_eq!(
+			steam
+				.resolve_vanity_url("alphakeks")
+				.await,
+			Ok(None)
+		);
+
+		steam.set_vanity_url(steam_id, "alphakeks");
+		assert_eq!(
+			steam
+				.resolve_vanity_url("alphakeks")
+				.await,
+			Ok(Some(steam_id))
+		);
+	}
+}
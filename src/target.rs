@@ -1,7 +1,9 @@
 use {
 	crate::{
 		error::Error,
+		players,
 		state::{Context, StateContainer},
+		steam::SteamClient,
 	},
 	gokz_rs::{PlayerIdentifier, SteamID},
 	lazy_static::lazy_static,
@@ -17,18 +19,30 @@ pub enum Target {
 	/// The user has @mention'd someone -> take the `UserID` from the mention
 	Mention { user_id: u64 },
 
-	/// The user has entered a [`SteamID`]
+	/// The user has entered a [`SteamID`] or a link to a Steam profile
 	SteamID { steam_id: SteamID },
 
+	/// The user has entered a link to a Steam profile with a custom URL, e.g.
+	/// `https://steamcommunity.com/id/alphakeks` -> we need to ask Steam for the [`SteamID`]
+	VanityURL { vanity_url: String },
+
 	/// The user has entered _something_ -> we interpret it as a name
 	Name { name: String },
 }
 
 impl Target {
-	pub async fn into_player(self, ctx: &Context<'_>) -> PlayerIdentifier {
-		match self {
+	/// Figures out which player the user meant. If the target is a name that matches multiple
+	/// players, the user will be asked to pick one.
+	pub async fn into_player(self, ctx: &Context<'_>) -> crate::error::Result<PlayerIdentifier> {
+		Ok(match self {
 			Target::SteamID { steam_id } => steam_id.into(),
-			Target::Name { name } => name.into(),
+			Target::VanityURL { vanity_url } => ctx
+				.steam()
+				.resolve_vanity_url(&vanity_url)
+				.await?
+				.ok_or(Error::PlayerNotFound { input: vanity_url })?
+				.into(),
+			Target::Name { name } => players::resolve_name(ctx, name).await?,
 			Target::None { user_id } | Target::Mention { user_id } => {
 				match ctx.fetch_user_by_id(user_id).await {
 					Some(user) => match user.steam_id {
@@ -38,7 +52,7 @@ impl Target {
 					None => ctx.author().name.clone().into(),
 				}
 			}
-		}
+		})
 	}
 }
 
@@ -48,6 +62,9 @@ impl std::fmt::Display for Target {
 			Self::None { user_id } => f.write_fmt(format_args!("<@{user_id}>")),
 			Self::Mention { user_id } => f.write_fmt(format_args!("<@{user_id}>")),
 			Self::SteamID { steam_id } => f.write_fmt(format_args!("{steam_id}")),
+			Self::VanityURL { vanity_url } => {
+				f.write_fmt(format_args!("https://steamcommunity.com/id/{vanity_url}"))
+			}
 			Self::Name { name } => f.write_fmt(format_args!("{name}")),
 		}
 	}
@@ -66,14 +83,29 @@ impl From<u64> for Target {
 }
 
 lazy_static! {
+	/// `<@291585142164815873>`, or `<@!291585142164815873>` if the user has a nickname
 	pub static ref MENTION_REGEX: Regex =
-		Regex::new(r#"^<@[0-9]+>$"#).expect("Failed to compile regex.");
+		Regex::new(r#"^<@!?([0-9]+)>$"#).expect("Failed to compile regex.");
+	pub static ref STEAM_PROFILE_REGEX: Regex = Regex::new(
+		r#"^(?:https?://)?(?:www\.)?steamcommunity\.com/profiles/([0-9]+)/?(?:[?#].*)?$"#
+	)
+	.expect("Failed to compile regex.");
+	pub static ref STEAM_VANITY_URL_REGEX: Regex = Regex::new(
+		r#"^(?:https?://)?(?:www\.)?steamcommunity\.com/id/([^/?#]+)/?(?:[?#].*)?$"#
+	)
+	.expect("Failed to compile regex.");
+	/// `https://kzgo.eu/players/STEAM_1:1:161178172?skz=`
+	pub static ref KZGO_PLAYER_REGEX: Regex =
+		Regex::new(r#"^(?:https?://)?(?:www\.)?kzgo\.eu/players/([^/?#]+)/?(?:[?#].*)?$"#)
+			.expect("Failed to compile regex.");
 }
 
 impl std::str::FromStr for Target {
 	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+
 		if s.is_empty() {
 			return Err(Error::EmptyInput {
 				expected: String::from("User identifier"),
@@ -84,13 +116,26 @@ impl std::str::FromStr for Target {
 			return Ok(Self::SteamID { steam_id });
 		}
 
-		if MENTION_REGEX.is_match(s) {
-			let user_id = s
-				.replace("<@", "")
-				.replace('>', "")
-				.parse::<u64>()
-				.unwrap();
+		// Links to Steam profiles and KZ:GO profiles both contain a SteamID, just in different
+		// formats.
+		if let Some(steam_id) = [
+			&*STEAM_PROFILE_REGEX, &*KZGO_PLAYER_REGEX,
+		]
+		.into_iter()
+		.find_map(|regex| regex.captures(s))
+		.and_then(|captures| SteamID::new(&captures[1]).ok())
+		{
+			return Ok(Self::SteamID { steam_id });
+		}
 
+		if let Some(captures) = STEAM_VANITY_URL_REGEX.captures(s) {
+			return Ok(Self::VanityURL { vanity_url: captures[1].to_owned() });
+		}
+
+		if let Some(user_id) = MENTION_REGEX
+			.captures(s)
+			.and_then(|captures| captures[1].parse::<u64>().ok())
+		{
 			return Ok(Self::Mention { user_id });
 		}
 
@@ -135,4 +180,77 @@ mod tests {
 		let parsed = mention.parse::<Target>();
 		assert_eq!(parsed, Ok(Target::Name { name: String::from(mention) }));
 	}
+
+	#[test]
+	fn nickname_mention() {
+		let mention = "<@!291585142164815873>";
+		let parsed = mention.parse::<Target>();
+		assert_eq!(parsed, Ok(Target::Mention { user_id: 291585142164815873 }));
+	}
+
+	#[test]
+	fn invalid_mention() {
+		// Too large for a `u64`
+		let mention = "<@99999999999999999999999>";
+		let parsed = mention.parse::<Target>();
+		assert_eq!(parsed, Ok(Target::Name { name: String::from(mention) }));
+	}
+
+	#[test]
+	fn steam_profile() {
+		let steam_id = SteamID::from_id32(322356345);
+
+		for input in [
+			"https://steamcommunity.com/profiles/76561198282622073",
+			"https://steamcommunity.com/profiles/76561198282622073/",
+			"http://www.steamcommunity.com/profiles/76561198282622073",
+			"steamcommunity.com/profiles/76561198282622073/?l=german",
+			"  https://steamcommunity.com/profiles/76561198282622073  ",
+		] {
+			assert_eq!(input.parse::<Target>(), Ok(Target::SteamID { steam_id }), "{input}");
+		}
+	}
+
+	#[test]
+	fn steam_vanity_url() {
+		for input in [
+			"https://steamcommunity.com/id/alphakeks",
+			"https://steamcommunity.com/id/alphakeks/",
+			"steamcommunity.com/id/alphakeks/?l=german",
+		] {
+			assert_eq!(
+				input.parse::<Target>(),
+				Ok(Target::VanityURL { vanity_url: String::from("alphakeks") }),
+				"{input}"
+			);
+		}
+	}
+
+	#[test]
+	fn kzgo_link() {
+		let steam_id = SteamID::from_id32(322356345);
+
+		for input in [
+			"https://kzgo.eu/players/STEAM_1:1:161178172",
+			"https://kzgo.eu/players/STEAM_1:1:161178172?skz=",
+			"kzgo.eu/players/76561198282622073/",
+		] {
+			assert_eq!(input.parse::<Target>(), Ok(Target::SteamID { steam_id }), "{input}");
+		}
+	}
+
+	#[test]
+	fn unrelated_links() {
+		// Neither of these contain a SteamID, so the best we can do is treat them as names.
+		for input in [
+			"https://steamcommunity.com/profiles/not-a-steam-id",
+			"https://kzgo.eu/maps/kz_lionharder",
+		] {
+			assert_eq!(
+				input.parse::<Target>(),
+				Ok(Target::Name { name: String::from(input) }),
+				"{input}"
+			);
+		}
+	}
 }
@@ -1,3 +1,9 @@
+use {
+	crate::error::{Error, Result},
+	gokz_rs::Mode,
+	schnosebot::global_map::GlobalMap,
+};
+
 pub fn format_replay_links(
 	tp_links: Option<(Option<String>, Option<String>)>,
 	pro_links: Option<(Option<String>, Option<String>)>,
@@ -39,3 +45,116 @@ pub fn format_replay_links(
 		}
 	}
 }
+
+/// Renders a percentage as a bar like `███░░░░░░░`, with `width` characters in total.
+pub fn progress_bar(percentage: f64, width: u32) -> String {
+	let filled = ((percentage / 100f64) * width as f64).clamp(0f64, width as f64) as u32;
+
+	(0..width)
+		.map(|i| if i < filled { '█' } else { '░' })
+		.collect()
+}
+
+/// Whether `map` has a filter for `mode`.
+pub fn has_filter(map: &GlobalMap, mode: Mode) -> bool {
+	match mode {
+		Mode::KZTimer => map.kzt,
+		Mode::SimpleKZ => map.skz,
+		Mode::Vanilla => map.vnl,
+	}
+}
+
+/// The GlobalAPI's name for a mode.
+pub fn api_mode(mode: Mode) -> &'static str {
+	match mode {
+		Mode::KZTimer => "kz_timer",
+		Mode::SimpleKZ => "kz_simple",
+		Mode::Vanilla => "kz_vanilla",
+	}
+}
+
+/// `kz_epiphany_v2 — T3 · KZT/SKZ`
+pub fn describe_map(map: &GlobalMap) -> String {
+	let filters = [
+		(map.kzt, "KZT"),
+		(map.skz, "SKZ"),
+		(map.vnl, "VNL"),
+	]
+	.into_iter()
+	.filter_map(|(has_filter, mode)| has_filter.then_some(mode))
+	.collect::<Vec<_>>();
+
+	let filters = if filters.is_empty() { String::from("no filters") } else { filters.join("/") };
+
+	format!("{} — T{} · {}", map.name, map.tier as u8, filters)
+}
+
+/// The bonus `course` the user asked for on `map`, defaulting to its first bonus. Fails if the map
+/// doesn't have that bonus, so we don't have to ask the GlobalAPI about courses that don't exist.
+pub fn bonus_course(map: &GlobalMap, course: Option<u8>) -> Result<u8> {
+	let mut bonuses = map
+		.courses
+		.iter()
+		.map(|course| course.stage)
+		.filter(|stage| *stage != 0)
+		.collect::<Vec<_>>();
+
+	bonuses.sort_unstable();
+
+	check_course(&map.name, &bonuses, course)
+}
+
+/// `bonuses` has to be sorted.
+fn check_course(map_name: &str, bonuses: &[u8], course: Option<u8>) -> Result<u8> {
+	let course = match course {
+		Some(course) if course != 0 => course,
+		_ => bonuses.first().copied().unwrap_or(1),
+	};
+
+	if bonuses.contains(&course) {
+		return Ok(course);
+	}
+
+	let valid = match bonuses {
+		[] => String::from("none, this map has no bonuses."),
+		bonuses => bonuses
+			.iter()
+			.map(|course| format!("`{course}`"))
+			.collect::<Vec<_>>()
+			.join(", "),
+	};
+
+	Err(Error::InvalidCourse { map: map_name.to_owned(), course, valid })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn courses() {
+		assert_eq!(check_course("kz_erratum_v2", &[1, 2], None), Ok(1));
+		assert_eq!(check_course("kz_erratum_v2", &[1, 2], Some(0)), Ok(1));
+		assert_eq!(check_course("kz_erratum_v2", &[1, 2], Some(2)), Ok(2));
+		assert_eq!(
+			check_course("kz_erratum_v2", &[1, 2], Some(7)),
+			Err(Error::InvalidCourse {
+				map: String::from("kz_erratum_v2"),
+				course: 7,
+				valid: String::from("`1`, `2`")
+			})
+		);
+		assert!(check_course("kz_beginnerblock_go", &[], None).is_err());
+
+		// Bonuses aren't always numbered without gaps.
+		assert_eq!(check_course("kz_gaps", &[2, 3], None), Ok(2));
+		assert_eq!(
+			check_course("kz_gaps", &[2, 3], Some(1)),
+			Err(Error::InvalidCourse {
+				map: String::from("kz_gaps"),
+				course: 1,
+				valid: String::from("`2`, `3`")
+			})
+		);
+	}
+}
@@ -0,0 +1,396 @@
+//! Background task that announces new world records in subscribed channels.
+//!
+//! The GlobalAPI is polled regularly for recent records that are in the top 1 of their map. Every
+//! record we have announced is stored in the database so we never announce the same record twice,
+//! even across restarts. Records are forgotten again once they drop out of the recent records. Channels can subscribe to the feed using `/wrfeed`.
+
+use {
+	crate::{error::Result, state::State, utils},
+	gokz_rs::{Mode, SteamID},
+	poise::serenity_prelude::{ChannelId, CreateEmbed, Http},
+	schnosebot::{global_map::GlobalMap, time},
+	serde::Deserialize,
+	sqlx::{FromRow, Pool, Postgres},
+	std::{sync::Arc, time::Duration},
+	tracing::{error, info, trace},
+};
+
+/// A record as returned by the GlobalAPI's `/records/top/recent` endpoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RecentRecord {
+	pub id: u32,
+	pub steamid64: String,
+	pub player_name: Option<String>,
+	pub map_id: u16,
+	pub map_name: String,
+	pub stage: u8,
+	pub time: f64,
+	pub teleports: u32,
+	pub points: u32,
+	pub replay_id: u32,
+}
+
+impl RecentRecord {
+	pub fn steam_id(&self) -> Option<SteamID> {
+		SteamID::new(&self.steamid64).ok()
+	}
+
+	pub fn replay_links(&self, base_url: &str) -> Option<(Option<String>, Option<String>)> {
+		if self.replay_id == 0 {
+			return None;
+		}
+
+		Some((
+			Some(format!("http://gokzstats.com/?replay={}", self.replay_id)),
+			Some(format!("{base_url}/records/replay/{}", self.replay_id)),
+		))
+	}
+}
+
+/// Fetches the most recent world records on main courses for a given mode and runtype.
+pub async fn fetch_recent_wrs(
+	client: &reqwest::Client,
+	base_url: &str,
+	mode: Mode,
+	runtype: bool,
+) -> Result<Vec<RecentRecord>> {
+	let records = client
+		.get(format!("{base_url}/records/top/recent"))
+		.query(&[
+			("modes_list_string", utils::api_mode(mode)),
+			("has_teleports", if runtype { "true" } else { "false" }),
+			("stage", "0"),
+			("tickrate", "128"),
+			("place_top_at_least", "1"),
+			("limit", "50"),
+		])
+		.send()
+		.await?
+		.error_for_status()?
+		.json::<Vec<RecentRecord>>()
+		.await?;
+
+	Ok(records
+		.into_iter()
+		.filter(|record| record.points == 1000)
+		.collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct SubscriptionRow {
+	pub channel_id: i64,
+	pub guild_id: i64,
+	pub mode: Option<i16>,
+	pub runtype: Option<bool>,
+	pub tier: Option<i16>,
+}
+
+impl SubscriptionRow {
+	/// Whether a record with the given properties should be announced in this channel.
+	pub fn matches(&self, mode: Mode, runtype: bool, tier: u8) -> bool {
+		self.mode
+			.map_or(true, |wanted| wanted == mode as u8 as i16)
+			&& self
+				.runtype
+				.map_or(true, |wanted| wanted == runtype)
+			&& self
+				.tier
+				.map_or(true, |min_tier| tier as i16 >= min_tier)
+	}
+}
+
+/// Spawns the background task that polls the GlobalAPI for new world records.
+pub fn spawn(http: Arc<Http>, state: State) {
+	tokio::spawn(async move {
+		let client = reqwest::Client::new();
+		let mut interval =
+			tokio::time::interval(Duration::from_secs(state.config.wr_feed_interval));
+
+		// If we have never seen any records before, we don't want to spam every subscribed
+		// channel with old world records. So we only remember them the first time around.
+		let mut announce = match has_seen_records(&state.database_connection).await {
+			Ok(has_seen_records) => has_seen_records,
+			Err(why) => {
+				error!("Failed to check for seen world records: {why:?}");
+				true
+			}
+		};
+
+		loop {
+			interval.tick().await;
+
+			trace!("Polling GlobalAPI for new world records.");
+
+			if let Err(why) = poll(&http, &state, &client, announce).await {
+				error!("Failed to poll for new world records: {why:?}");
+				continue;
+			}
+
+			announce = true;
+		}
+	});
+}
+
+async fn has_seen_records(db: &Pool<Postgres>) -> Result<bool> {
+	let (exists,): (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM wr_feed_records)")
+		.fetch_one(db)
+		.await?;
+
+	Ok(exists)
+}
+
+async fn has_seen(record_id: u32, db: &Pool<Postgres>) -> Result<bool> {
+	let (exists,): (bool,) =
+		sqlx::query_as("SELECT EXISTS (SELECT 1 FROM wr_feed_records WHERE record_id = $1)")
+			.bind(record_id as i64)
+			.fetch_one(db)
+			.await?;
+
+	Ok(exists)
+}
+
+async fn mark_as_seen(record_id: u32, db: &Pool<Postgres>) -> Result<()> {
+	sqlx::query(
+		"INSERT INTO wr_feed_records (record_id) VALUES ($1) ON CONFLICT (record_id) DO NOTHING",
+	)
+	.bind(record_id as i64)
+	.execute(db)
+	.await?;
+
+	Ok(())
+}
+
+/// Forgets every record that isn't among `record_ids` anymore. Those have dropped out of the
+/// recent records we poll, so they can't show up again.
+async fn forget_old_records(record_ids: &[i64], db: &Pool<Postgres>) -> Result<()> {
+	sqlx::query("DELETE FROM wr_feed_records WHERE record_id <> ALL($1)")
+		.bind(record_ids)
+		.execute(db)
+		.await?;
+
+	Ok(())
+}
+
+async fn poll(http: &Http, state: &State, client: &reqwest::Client, announce: bool) -> Result<()> {
+	let db = &state.database_connection;
+	let base_url = &state.config.global_api_url;
+
+	let subscriptions = sqlx::query_as::<_, SubscriptionRow>("SELECT * FROM wr_feed_subscriptions")
+		.fetch_all(db)
+		.await?;
+
+	let maps = state.global_maps.maps();
+	let mut polled_ids = Vec::new();
+
+	for mode in [
+		Mode::KZTimer,
+		Mode::SimpleKZ,
+		Mode::Vanilla,
+	] {
+		for runtype in [true, false] {
+			let mut records = fetch_recent_wrs(client, base_url, mode, runtype).await?;
+
+			// Oldest records first, so the feed is in chronological order.
+			records.sort_unstable_by_key(|record| record.id);
+
+			for record in records {
+				polled_ids.push(record.id as i64);
+
+				if has_seen(record.id, db).await? {
+					continue;
+				}
+
+				let map = maps
+					.iter()
+					.find(|map| map.id == record.map_id);
+
+				let (Some(map), true) = (map, announce) else {
+					mark_as_seen(record.id, db).await?;
+					continue;
+				};
+
+				info!("New WR on `{}` by `{:?}`.", map.name, record.player_name);
+
+				let embed = build_embed(&record, map, mode, runtype, state);
+				let mut posted = false;
+				let mut failed = false;
+
+				for subscription in subscriptions
+					.iter()
+					.filter(|sub| sub.matches(mode, runtype, map.tier as u8))
+				{
+					match ChannelId(subscription.channel_id as u64)
+						.send_message(http, |message| message.set_embed(embed.clone()))
+						.await
+					{
+						Ok(_) => posted = true,
+						Err(why) => {
+							failed = true;
+							error!(
+								"Failed to post WR in channel `{}`: {why:?}",
+								subscription.channel_id
+							);
+						}
+					}
+				}
+
+				// If no channel got the record, we try again next time. Otherwise we'd post it
+				// twice in the channels that did get it.
+				if posted || !failed {
+					mark_as_seen(record.id, db).await?;
+				}
+			}
+		}
+	}
+
+	// An empty response shouldn't make us forget everything we've already announced.
+	if !polled_ids.is_empty() {
+		forget_old_records(&polled_ids, db).await?;
+	}
+
+	Ok(())
+}
+
+fn build_embed(
+	record: &RecentRecord,
+	map: &GlobalMap,
+	mode: Mode,
+	runtype: bool,
+	state: &State,
+) -> CreateEmbed {
+	let time = time::format(record.time);
+	let teleports = match record.teleports {
+		0 => String::new(),
+		1 => String::from("(1 TP)"),
+		n => format!("({n} TPs)"),
+	};
+
+	let player_name = record
+		.player_name
+		.clone()
+		.unwrap_or_else(|| String::from("unknown"));
+
+	let player_name = match record.steam_id() {
+		Some(steam_id) => format!("[{player_name}](https://kzgo.eu/players/{steam_id})"),
+		None => player_name,
+	};
+
+	let replay_links = record.replay_links(&state.config.global_api_url);
+	let (tp_links, pro_links) = if runtype { (replay_links, None) } else { (None, replay_links) };
+
+	let mut embed = CreateEmbed::default();
+	embed
+		.color(state.color)
+		.title(format!("[WR] {map_name}", map_name = &map.name))
+		.url(format!("{link}?{mode}=", link = map.kzgo_link(), mode = mode.short().to_lowercase()))
+		.thumbnail(map.thumbnail())
+		.description(utils::format_replay_links(tp_links, pro_links).unwrap_or_default())
+		.field(
+			if runtype { "TP" } else { "PRO" },
+			format!("{time} {teleports}\n> by {player_name}"),
+			true,
+		)
+		.footer(|footer| {
+			footer
+				.text(format!(
+					"{} | Mode: {} | Tier: {}",
+					state.schnose,
+					mode.short(),
+					map.tier as u8
+				))
+				.icon_url(&state.icon_url)
+		});
+
+	embed
+}
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		tokio::{
+			io::{AsyncReadExt, AsyncWriteExt},
+			net::TcpListener,
+		},
+	};
+
+	/// Spins up a fake GlobalAPI that answers exactly one request with `body`.
+	async fn fake_api(body: &'static str) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap();
+		let addr = listener.local_addr().unwrap();
+
+		tokio::spawn(async move {
+			let (mut socket, _) = listener.accept().await.unwrap();
+			let mut buf = [0; 4096];
+			let _ = socket.read(&mut buf).await.unwrap();
+
+			let response = format!(
+				"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+				body.len()
+			);
+
+			socket
+				.write_all(response.as_bytes())
+				.await
+				.unwrap();
+		});
+
+		format!("http://{addr}")
+	}
+
+	#[tokio::test]
+	async fn fetch_only_wrs() {
+		let base_url = fake_api(
+			r#"[
+				{ "id": 1, "steamid64": "76561198282622073", "player_name": "AlphaKeks", "map_id": 992, "map_name": "kz_lionharder", "stage": 0, "time": 1337.69, "teleports": 0, "points": 1000, "replay_id": 42 },
+				{ "id": 2, "steamid64": "76561198282622073", "player_name": "AlphaKeks", "map_id": 993, "map_name": "kz_epiphany_v2", "stage": 0, "time": 69.42, "teleports": 0, "points": 985, "replay_id": 0 }
+			]"#,
+		)
+		.await;
+
+		let records = fetch_recent_wrs(&reqwest::Client::new(), &base_url, Mode::KZTimer, false)
+			.await
+			.unwrap();
+
+		assert_eq!(records.len(), 1);
+		assert_eq!(records[0].id, 1);
+		assert_eq!(records[0].map_name, "kz_lionharder");
+		assert_eq!(records[0].steam_id(), Some(SteamID::from_id32(322356345)));
+		assert_eq!(
+			records[0].replay_links(&base_url),
+			Some((
+				Some(String::from("http://gokzstats.com/?replay=42")),
+				Some(format!("{base_url}/records/replay/42"))
+			))
+		);
+	}
+
+	#[test]
+	fn subscription_filters() {
+		let subscription = SubscriptionRow {
+			channel_id: 0,
+			guild_id: 0,
+			mode: Some(Mode::SimpleKZ as u8 as i16),
+			runtype: Some(false),
+			tier: Some(5),
+		};
+
+		assert!(subscription.matches(Mode::SimpleKZ, false, 5));
+		assert!(subscription.matches(Mode::SimpleKZ, false, 7));
+		assert!(!subscription.matches(Mode::SimpleKZ, false, 4));
+		assert!(!subscription.matches(Mode::SimpleKZ, true, 6));
+		assert!(!subscription.matches(Mode::KZTimer, false, 6));
+
+		let subscription = SubscriptionRow {
+			channel_id: 0,
+			guild_id: 0,
+			mode: None,
+			runtype: None,
+			tier: None,
+		};
+
+		assert!(subscription.matches(Mode::Vanilla, true, 1));
+	}
+}